@@ -0,0 +1,249 @@
+//! Bisection over predicates returning futures.
+//!
+//! These mirror [`bisect_left_by`](crate::bisect_left_by) and
+//! [`bisect_right_by`](crate::bisect_right_by) for oracles that are asynchronous operations, such
+//! as spawning a job or querying a service. The comparator is called with the index to probe
+//! rather than an element. Nothing here depends on a particular executor.
+//!
+//! The `_speculative` variants evaluate the midpoint together with both of the midpoints that
+//! could follow it, then keep whichever of the two turns out to be needed. This takes three
+//! evaluations per round instead of one, but each round covers two levels of the search, halving
+//! the wall-clock time when evaluations run concurrently.
+
+use std::cmp::Ordering;
+use std::future::{poll_fn, Future};
+use std::ops::Range;
+use std::task::Poll;
+
+/// Return the index in `within` where a value should be inserted, according to an asynchronous
+/// comparator function.
+///
+/// `f(i)` should resolve to the comparison of the element at index `i` with the desired target,
+/// with the same contract as [`bisect_left_by`](crate::bisect_left_by).
+pub async fn bisect_left_async<F, Fut>(within: Range<usize>, f: F) -> usize
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Ordering>,
+{
+    partition_async(within, f, |cmp| cmp == Ordering::Less).await
+}
+
+/// Return the index in `within` where a value should be inserted, according to an asynchronous
+/// comparator function.
+///
+/// `f(i)` should resolve to the comparison of the desired target with the element at index `i`,
+/// with the same contract as [`bisect_right_by`](crate::bisect_right_by).
+pub async fn bisect_right_async<F, Fut>(within: Range<usize>, f: F) -> usize
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Ordering>,
+{
+    partition_async(within, f, |cmp| cmp != Ordering::Less).await
+}
+
+/// [`bisect_left_async`], evaluating the next two possible midpoints concurrently with each
+/// midpoint.
+pub async fn bisect_left_async_speculative<F, Fut>(within: Range<usize>, f: F) -> usize
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Ordering>,
+{
+    partition_async_speculative(within, f, |cmp| cmp == Ordering::Less).await
+}
+
+/// [`bisect_right_async`], evaluating the next two possible midpoints concurrently with each
+/// midpoint.
+pub async fn bisect_right_async_speculative<F, Fut>(within: Range<usize>, f: F) -> usize
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Ordering>,
+{
+    partition_async_speculative(within, f, |cmp| cmp != Ordering::Less).await
+}
+
+async fn partition_async<F, Fut, P>(within: Range<usize>, mut f: F, go_right: P) -> usize
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Ordering>,
+    P: Fn(Ordering) -> bool,
+{
+    let (mut lo, mut hi) = (within.start, within.end);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if go_right(f(mid).await) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+async fn partition_async_speculative<F, Fut, P>(
+    within: Range<usize>,
+    mut f: F,
+    go_right: P,
+) -> usize
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Ordering>,
+    P: Fn(Ordering) -> bool,
+{
+    let (mut lo, mut hi) = (within.start, within.end);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let left = (lo < mid).then(|| (lo + mid) / 2);
+        let right = (mid + 1 < hi).then(|| (mid + 1 + hi) / 2);
+
+        let probes: Vec<usize> = [Some(mid), left, right].iter().flatten().copied().collect();
+        let results = join_all(probes.iter().map(|&i| f(i)).collect()).await;
+        let result = |i| results[probes.iter().position(|&p| p == i).unwrap()];
+
+        let next = if go_right(result(mid)) {
+            lo = mid + 1;
+            right
+        } else {
+            hi = mid;
+            left
+        };
+        if let Some(next) = next {
+            if go_right(result(next)) {
+                lo = next + 1;
+            } else {
+                hi = next;
+            }
+        }
+    }
+    lo
+}
+
+/// Wait for all of `futures` to complete, polling them concurrently.
+async fn join_all<Fut: Future>(futures: Vec<Fut>) -> Vec<Fut::Output> {
+    let mut pending: Vec<_> = futures.into_iter().map(Box::pin).collect();
+    let mut outputs: Vec<Option<Fut::Output>> = pending.iter().map(|_| None).collect();
+    poll_fn(|cx| {
+        let mut done = true;
+        for (future, output) in pending.iter_mut().zip(outputs.iter_mut()) {
+            if output.is_none() {
+                match future.as_mut().poll(cx) {
+                    Poll::Ready(value) => *output = Some(value),
+                    Poll::Pending => done = false,
+                }
+            }
+        }
+        if done {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+    outputs.into_iter().map(Option::unwrap).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bisect_left, bisect_right};
+    use proptest::prelude::*;
+    use std::cell::Cell;
+    use std::pin::Pin;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::task::{Context, Wake, Waker};
+    use std::thread::{self, Thread};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    /// Future which is pending the first time it is polled.
+    struct YieldOnce<T>(Option<T>, bool);
+
+    impl<T: Unpin> Future for YieldOnce<T> {
+        type Output = T;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+            if self.1 {
+                Poll::Ready(self.0.take().unwrap())
+            } else {
+                self.1 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Count the rounds of concurrent probes a search makes, where a round is a batch of calls
+    /// to the comparator made before any of the resulting futures are polled.
+    fn rounds<S, Fut>(search: S) -> (usize, usize)
+    where
+        S: FnOnce(Box<dyn FnMut(usize) -> Pin<Box<dyn Future<Output = Ordering>>>>) -> Fut,
+        Fut: Future<Output = usize>,
+    {
+        let polled = Rc::new(Cell::new(true));
+        let rounds = Rc::new(Cell::new(0));
+        let (p, r) = (polled.clone(), rounds.clone());
+        let i = block_on(search(Box::new(move |i| {
+            if p.replace(false) {
+                r.set(r.get() + 1);
+            }
+            let p = p.clone();
+            Box::pin(async move {
+                p.set(true);
+                YieldOnce(Some((i as u32).cmp(&700)), false).await
+            })
+        })));
+        (i, rounds.get())
+    }
+
+    #[test]
+    fn speculative_takes_fewer_rounds() {
+        assert_eq!(rounds(|f| bisect_left_async(0..1024, f)), (700, 10));
+        assert_eq!(
+            rounds(|f| bisect_left_async_speculative(0..1024, f)),
+            (700, 5)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_bisect(
+            mut nums in prop::collection::vec(0..100_u32, 0..200),
+            num in 0..100_u32
+        ) {
+            nums.sort();
+            let left = |i: usize| YieldOnce(Some(nums[i].cmp(&num)), false);
+            let right = |i: usize| YieldOnce(Some(num.cmp(&nums[i])), false);
+            let expected_left = bisect_left(&nums, &num);
+            let expected_right = bisect_right(&nums, &num);
+
+            prop_assert_eq!(block_on(bisect_left_async(0..nums.len(), left)), expected_left);
+            prop_assert_eq!(
+                block_on(bisect_left_async_speculative(0..nums.len(), left)),
+                expected_left
+            );
+            prop_assert_eq!(block_on(bisect_right_async(0..nums.len(), right)), expected_right);
+            prop_assert_eq!(
+                block_on(bisect_right_async_speculative(0..nums.len(), right)),
+                expected_right
+            );
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Minimal command line parsing.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+/// Error which stops a command, reported with exit status 2.
+#[derive(Debug)]
+pub enum Error {
+    Usage(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(msg) => write!(f, "{}", msg),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Return a usage error with the given message.
+pub fn usage<T>(msg: impl Into<String>) -> Result<T, Error> {
+    Err(Error::Usage(msg.into()))
+}
+
+/// An option accepted by a command.
+#[derive(Clone, Copy, Debug)]
+pub struct Opt {
+    pub long: &'static str,
+    pub short: Option<char>,
+    pub takes_value: bool,
+}
+
+/// Options and positional arguments of a command.
+#[derive(Debug, Default)]
+pub struct Args {
+    pub positional: Vec<String>,
+    /// Arguments after `--`, passed through untouched.
+    pub trailing: Vec<String>,
+    values: HashMap<&'static str, String>,
+}
+
+impl Args {
+    /// Parse `args` according to `opts`, allowing options and positional arguments in any
+    /// order.
+    pub fn parse<I>(args: I, opts: &[Opt]) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.trailing.extend(args.by_ref());
+                break;
+            }
+            let (opt, inline) = if let Some(long) = arg.strip_prefix("--") {
+                let (name, value) = match long.find('=') {
+                    Some(i) => (&long[..i], Some(long[i + 1..].to_string())),
+                    None => (long, None),
+                };
+                match opts.iter().find(|o| o.long == name) {
+                    Some(opt) => (opt, value),
+                    None => return usage(format!("unknown option --{}", name)),
+                }
+            } else if arg.len() > 1 && arg.starts_with('-') {
+                let mut chars = arg[1..].chars();
+                let c = chars.next().unwrap();
+                let rest = chars.as_str();
+                match opts.iter().find(|o| o.short == Some(c)) {
+                    Some(opt) => (opt, Some(rest.to_string()).filter(|r| !r.is_empty())),
+                    None => return usage(format!("unknown option -{}", c)),
+                }
+            } else {
+                parsed.positional.push(arg);
+                continue;
+            };
+
+            let value = if !opt.takes_value {
+                if inline.is_some() {
+                    return usage(format!("option --{} takes no value", opt.long));
+                }
+                String::new()
+            } else if let Some(value) = inline {
+                value
+            } else {
+                match args.next() {
+                    Some(value) => value,
+                    None => return usage(format!("option --{} needs a value", opt.long)),
+                }
+            };
+            parsed.values.insert(opt.long, value);
+        }
+        Ok(parsed)
+    }
+
+    /// Return the value of the option `long`, if given.
+    pub fn value(&self, long: &str) -> Option<&str> {
+        self.values.get(long).map(String::as_str)
+    }
+
+    /// Return `true` if the option `long` was given.
+    pub fn flag(&self, long: &str) -> bool {
+        self.values.contains_key(long)
+    }
+
+    /// Return the positional arguments, checking there are as many as `names`.
+    pub fn expect_positional(&self, names: &[&str]) -> Result<&[String], Error> {
+        if self.positional.len() != names.len() {
+            let names: Vec<_> = names.iter().map(|n| format!("<{}>", n)).collect();
+            return usage(format!("expected arguments {}", names.join(" ")));
+        }
+        Ok(&self.positional)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTS: &[Opt] = &[
+        Opt {
+            long: "numeric",
+            short: Some('n'),
+            takes_value: false,
+        },
+        Opt {
+            long: "key",
+            short: Some('k'),
+            takes_value: true,
+        },
+    ];
+
+    fn parse(args: &[&str]) -> Result<Args, Error> {
+        Args::parse(args.iter().map(|a| a.to_string()), OPTS)
+    }
+
+    #[test]
+    fn options() {
+        let args = parse(&["file", "-n", "--key=2", "x", "--", "-k", "y"]).unwrap();
+        assert_eq!(args.positional, ["file", "x"]);
+        assert_eq!(args.trailing, ["-k", "y"]);
+        assert!(args.flag("numeric"));
+        assert_eq!(args.value("key"), Some("2"));
+
+        assert_eq!(parse(&["-k3"]).unwrap().value("key"), Some("3"));
+        assert_eq!(parse(&["-k", "3"]).unwrap().value("key"), Some("3"));
+        assert_eq!(parse(&["-"]).unwrap().positional, ["-"]);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse(&["--bogus"]).is_err());
+        assert!(parse(&["-x"]).is_err());
+        assert!(parse(&["--key"]).is_err());
+        assert!(parse(&["--numeric=yes"]).is_err());
+    }
+}
@@ -0,0 +1,360 @@
+//! Command line interface to searching sorted files.
+
+mod args;
+mod order;
+mod run;
+
+use crate::args::{usage, Args, Error, Opt};
+use crate::order::{KeySpec, Order};
+use bisection::{bisect_right_by, file};
+use std::cmp::Ordering;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::process;
+
+const USAGE: &str = "\
+usage: bisection <command> [options] [arguments]
+
+commands:
+    look <file> <key>      print the lines of a sorted file whose key starts with <key>,
+                           or equals it for numeric and version orders
+    range <file>           print the lines of a sorted file with keys in a range
+        --from <key>       smallest key to print
+        --to <key>         largest key to print
+    bucket                 label each line of standard input with its bucket
+        --breakpoints <b>  comma-separated sorted bucket boundaries
+        --labels <l>       comma-separated labels, one more than breakpoints
+    check <file>           check that a file is sorted, reporting the first line out of order
+    run -- <command>       find the first value for which <command> fails, with {} in its
+                           arguments replaced by the value tested, or the value appended;
+                           exit status 0 is good, 125 skip and any other up to 127 bad
+        --range <a..=b>    test the integers from a to b, or to b - 1 with a..b
+        --candidates <f>   test the non-empty lines of <f>, in order
+        --timeout <secs>   kill a test running longer than <secs> and count it as bad
+        --log <f>          save progress to <f>, resuming from it if it exists
+
+ordering options:
+    --order <order>        lexicographic (default), numeric or version
+    -n, --numeric          same as --order numeric
+    -V, --version-sort     same as --order version
+    -t, --separator <c>    split fields on <c> rather than on whitespace
+    -k, --key <n>          compare field <n>, counting from 1, rather than whole lines
+
+Exit status is 0 on success, 1 if look finds nothing, check finds disorder or run finds no
+single first bad value, and 2 on error.";
+
+fn main() {
+    let stdin = io::stdin();
+    let mut out = BufWriter::new(io::stdout());
+    let result = run(
+        env::args().skip(1).collect(),
+        &mut stdin.lock(),
+        &mut out,
+        &mut io::stderr(),
+    )
+    .and_then(|code| out.flush().map(|_| code).map_err(Error::from));
+    let code = match result {
+        Ok(code) => code,
+        Err(Error::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => 0,
+        Err(e) => {
+            eprintln!("bisection: {}", e);
+            if let Error::Usage(_) = e {
+                eprintln!("Try 'bisection help' for more information.");
+            }
+            2
+        }
+    };
+    process::exit(code);
+}
+
+/// Run the command given by `args`, reading standard input from `input` and writing standard
+/// output and error to `out` and `err`, and return the exit status.
+fn run(
+    mut args: Vec<String>,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<i32, Error> {
+    if args.is_empty() {
+        return usage("no command given");
+    }
+    let command = args.remove(0);
+    match command.as_str() {
+        "look" => look(&parse(args, &[])?, out),
+        "range" => range(&parse(args, &[value_opt("from"), value_opt("to")])?, out),
+        "bucket" => bucket(
+            &parse(args, &[value_opt("breakpoints"), value_opt("labels")])?,
+            input,
+            out,
+        ),
+        "check" => check(&parse(args, &[])?, input, err),
+        "run" => run::run(&Args::parse(
+            args,
+            &[
+                value_opt("range"),
+                value_opt("candidates"),
+                value_opt("timeout"),
+                value_opt("log"),
+            ],
+        )?),
+        "help" | "-h" | "--help" => {
+            writeln!(out, "{}", USAGE)?;
+            Ok(0)
+        }
+        _ => usage(format!("unknown command {:?}", command)),
+    }
+}
+
+fn value_opt(long: &'static str) -> Opt {
+    Opt {
+        long,
+        short: None,
+        takes_value: true,
+    }
+}
+
+/// Parse the arguments of a command taking the ordering options and `extra`.
+fn parse(args: Vec<String>, extra: &[Opt]) -> Result<Args, Error> {
+    let opts: Vec<Opt> = order::OPTS.iter().chain(extra).copied().collect();
+    Args::parse(args, &opts)
+}
+
+fn look(args: &Args, out: &mut dyn Write) -> Result<i32, Error> {
+    let positional = args.expect_positional(&["file", "key"])?;
+    let spec = KeySpec::from_args(args, Order::Lexicographic)?;
+    let target = positional[1].as_bytes();
+    let mut f = File::open(&positional[0])?;
+
+    let found = file::equal_range_by(&mut f, |line| {
+        let key = spec.key(line);
+        if spec.order == Order::Lexicographic {
+            key[..key.len().min(target.len())].cmp(target)
+        } else {
+            spec.compare(key, target)
+        }
+    })?;
+    copy_range(&mut f, found.clone(), out)?;
+    Ok(if found.is_empty() { 1 } else { 0 })
+}
+
+fn range(args: &Args, out: &mut dyn Write) -> Result<i32, Error> {
+    let positional = args.expect_positional(&["file"])?;
+    let spec = KeySpec::from_args(args, Order::Lexicographic)?;
+    let mut f = File::open(&positional[0])?;
+
+    let start = match args.value("from") {
+        Some(from) => {
+            file::bisect_left_by(&mut f, |line| spec.compare(spec.key(line), from.as_bytes()))?
+        }
+        None => 0,
+    };
+    let end = match args.value("to") {
+        Some(to) => {
+            file::bisect_right_by(&mut f, |line| spec.compare(to.as_bytes(), spec.key(line)))?
+        }
+        None => f.seek(SeekFrom::End(0))?,
+    };
+    copy_range(&mut f, start..end.max(start), out)?;
+    Ok(0)
+}
+
+fn bucket(args: &Args, input: &mut dyn BufRead, out: &mut dyn Write) -> Result<i32, Error> {
+    args.expect_positional(&[])?;
+    // Bucketing is almost always of numbers, such as scores.
+    let spec = KeySpec::from_args(args, Order::Numeric)?;
+    let breakpoints: Vec<&[u8]> = match args.value("breakpoints") {
+        Some(b) => b.split(',').map(str::as_bytes).collect(),
+        None => return usage("missing --breakpoints"),
+    };
+    if breakpoints
+        .windows(2)
+        .any(|w| spec.compare(w[0], w[1]) == Ordering::Greater)
+    {
+        return usage("breakpoints must be sorted");
+    }
+    let labels: Vec<String> = match args.value("labels") {
+        Some(l) => l.split(',').map(String::from).collect(),
+        None => (0..=breakpoints.len()).map(|i| i.to_string()).collect(),
+    };
+    if labels.len() != breakpoints.len() + 1 {
+        return usage(format!(
+            "expected {} labels for {} breakpoints",
+            breakpoints.len() + 1,
+            breakpoints.len()
+        ));
+    }
+
+    for line in input.split(b'\n') {
+        let mut line = line?;
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        let key = spec.key(&line);
+        let i = bisect_right_by(&breakpoints, |b| spec.compare(key, b));
+        out.write_all(&line)?;
+        writeln!(out, "\t{}", labels[i])?;
+    }
+    Ok(0)
+}
+
+fn check(args: &Args, input: &mut dyn BufRead, err: &mut dyn Write) -> Result<i32, Error> {
+    let positional = args.expect_positional(&["file"])?;
+    let spec = KeySpec::from_args(args, Order::Lexicographic)?;
+    let name = &positional[0];
+    let input: Box<dyn BufRead + '_> = if name == "-" {
+        Box::new(input)
+    } else {
+        Box::new(BufReader::new(File::open(name)?))
+    };
+
+    let mut previous: Option<Vec<u8>> = None;
+    for (n, line) in input.split(b'\n').enumerate() {
+        let mut line = line?;
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        if let Some(previous) = &previous {
+            if spec.compare(spec.key(previous), spec.key(&line)) == Ordering::Greater {
+                writeln!(
+                    err,
+                    "bisection: {}:{}: disorder: {}",
+                    name,
+                    n + 1,
+                    String::from_utf8_lossy(&line)
+                )?;
+                return Ok(1);
+            }
+        }
+        previous = Some(line);
+    }
+    Ok(0)
+}
+
+/// Copy the bytes `range` of `f` to `out`, ending with a newline.
+fn copy_range(f: &mut File, range: Range<u64>, out: &mut dyn Write) -> io::Result<()> {
+    if range.is_empty() {
+        return Ok(());
+    }
+    let mut last = [0];
+    f.seek(SeekFrom::Start(range.end - 1))?;
+    f.read_exact(&mut last)?;
+    f.seek(SeekFrom::Start(range.start))?;
+    io::copy(&mut f.take(range.end - range.start), out)?;
+    if last[0] != b'\n' {
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Run `args` with `input` as standard input, returning the exit status, standard output
+    /// and standard error.
+    fn exec(args: &[&str], input: &str) -> Result<(i32, String, String), Error> {
+        let (mut out, mut err) = (vec![], vec![]);
+        let args = args.iter().map(|a| a.to_string()).collect();
+        let code = run(args, &mut input.as_bytes(), &mut out, &mut err)?;
+        let text = |bytes| String::from_utf8(bytes).unwrap();
+        Ok((code, text(out), text(err)))
+    }
+
+    /// Write `contents` to a temporary file named after `name` and return its path.
+    fn temp(name: &str, contents: &str) -> String {
+        let path = env::temp_dir().join(format!("bisection-{}-{}", name, process::id()));
+        fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn commands() {
+        assert!(matches!(exec(&[], ""), Err(Error::Usage(_))));
+        assert!(matches!(exec(&["sort"], ""), Err(Error::Usage(_))));
+        let (code, out, _) = exec(&["help"], "").unwrap();
+        assert_eq!(code, 0);
+        assert!(out.contains("--version-sort"));
+    }
+
+    #[test]
+    fn look() {
+        let path = temp("look", "apple 1\nbanana 2\nbanana 3\ncherry 4");
+        let (code, out, _) = exec(&["look", &path, "ban"], "").unwrap();
+        assert_eq!((code, out.as_str()), (0, "banana 2\nbanana 3\n"));
+        // The last line gains the newline it lacks.
+        let (code, out, _) = exec(&["look", &path, "cherry"], "").unwrap();
+        assert_eq!((code, out.as_str()), (0, "cherry 4\n"));
+        let (code, out, _) = exec(&["look", &path, "blueberry"], "").unwrap();
+        assert_eq!((code, out.as_str()), (1, ""));
+        let (code, out, _) = exec(&["look", "-k", "2", "-n", &path, "3"], "").unwrap();
+        assert_eq!((code, out.as_str()), (0, "banana 3\n"));
+
+        assert!(matches!(exec(&["look", &path], ""), Err(Error::Usage(_))));
+        assert!(matches!(
+            exec(&["look", "--bogus", &path, "a"], ""),
+            Err(Error::Usage(_))
+        ));
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(exec(&["look", &path, "a"], ""), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn range() {
+        let path = temp("range", "1\n5\n5\n10\n20\n");
+        let numeric = |from: &str, to: &str| {
+            let args = ["range", "-n", "--from", from, "--to", to, &path];
+            exec(&args, "").unwrap()
+        };
+        assert_eq!(numeric("5", "10"), (0, "5\n5\n10\n".into(), "".into()));
+        assert_eq!(numeric("6", "9"), (0, "".into(), "".into()));
+        assert_eq!(numeric("20", "1"), (0, "".into(), "".into()));
+        let (code, out, _) = exec(&["range", "-n", "--from", "10", &path], "").unwrap();
+        assert_eq!((code, out.as_str()), (0, "10\n20\n"));
+        let (code, out, _) = exec(&["range", &path], "").unwrap();
+        assert_eq!((code, out.as_str()), (0, "1\n5\n5\n10\n20\n"));
+        assert!(matches!(
+            exec(&["range", "--order", "random", &path], ""),
+            Err(Error::Usage(_))
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn bucket() {
+        let grades = ["bucket", "--breakpoints", "60,80", "--labels", "F,B,A"];
+        let (code, out, _) = exec(&grades, "50\n60\n79.5\r\n90\n").unwrap();
+        assert_eq!(code, 0);
+        assert_eq!(out, "50\tF\n60\tB\n79.5\tB\n90\tA\n");
+        let (_, out, _) = exec(&["bucket", "--breakpoints", "5", "-k", "2"], "a 9\nb 1\n").unwrap();
+        assert_eq!(out, "a 9\t1\nb 1\t0\n");
+
+        for args in [
+            &["bucket"][..],
+            &["bucket", "--breakpoints", "80,60"],
+            &["bucket", "--breakpoints", "60,80", "--labels", "F,A"],
+            &["bucket", "--breakpoints", "60", "extra"],
+        ]
+        .iter()
+        {
+            assert!(matches!(exec(args, ""), Err(Error::Usage(_))));
+        }
+    }
+
+    #[test]
+    fn check() {
+        assert_eq!(exec(&["check", "-"], "a\nb\nb\n").unwrap().0, 0);
+        let (code, _, err) = exec(&["check", "-"], "a\nc\nb\n").unwrap();
+        assert_eq!(code, 1);
+        assert_eq!(err, "bisection: -:3: disorder: b\n");
+        assert_eq!(exec(&["check", "-n", "-"], "9\n10\n").unwrap().0, 0);
+        assert_eq!(exec(&["check", "-"], "9\n10\n").unwrap().0, 1);
+
+        let path = temp("check", "1\n2\n3\n");
+        assert_eq!(exec(&["check", &path], "3\n1\n").unwrap().0, 0);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(exec(&["check"], ""), Err(Error::Usage(_))));
+    }
+}
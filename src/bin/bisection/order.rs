@@ -0,0 +1,176 @@
+//! Key extraction and ordering of lines, shared by the commands.
+
+use crate::args::{usage, Args, Error, Opt};
+use bisection::compare;
+use std::cmp::Ordering;
+
+/// Options selecting the key of each line and how keys are ordered.
+pub const OPTS: &[Opt] = &[
+    Opt {
+        long: "order",
+        short: None,
+        takes_value: true,
+    },
+    Opt {
+        long: "numeric",
+        short: Some('n'),
+        takes_value: false,
+    },
+    Opt {
+        long: "version-sort",
+        short: Some('V'),
+        takes_value: false,
+    },
+    Opt {
+        long: "separator",
+        short: Some('t'),
+        takes_value: true,
+    },
+    Opt {
+        long: "key",
+        short: Some('k'),
+        takes_value: true,
+    },
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Order {
+    /// Bytewise, as `sort` with `LC_ALL=C`.
+    Lexicographic,
+    /// Decimal numbers, with keys which are not numbers before all numbers.
+    Numeric,
+    /// Runs of digits compared numerically and other runs bytewise, as in `1.9 < 1.10`.
+    Version,
+}
+
+/// How to find and compare the key of a line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeySpec {
+    pub order: Order,
+    /// Field separator, or `None` to split fields on runs of whitespace.
+    separator: Option<u8>,
+    /// Index of the key field, or `None` to use the whole line.
+    field: Option<usize>,
+}
+
+impl KeySpec {
+    /// Read the ordering options, using `default` if no order is given.
+    pub fn from_args(args: &Args, default: Order) -> Result<Self, Error> {
+        let order = match (
+            args.value("order"),
+            args.flag("numeric"),
+            args.flag("version-sort"),
+        ) {
+            (None, false, false) => default,
+            (Some("lexicographic"), false, false) => Order::Lexicographic,
+            (None, true, false) | (Some("numeric"), false, false) => Order::Numeric,
+            (None, false, true) | (Some("version"), false, false) => Order::Version,
+            (Some(o), false, false) => return usage(format!("unknown order {:?}", o)),
+            _ => return usage("conflicting orders given"),
+        };
+        let separator = match args.value("separator") {
+            None => None,
+            Some(s) if s.len() == 1 => Some(s.as_bytes()[0]),
+            Some("\\t") => Some(b'\t'),
+            Some(s) => return usage(format!("separator must be a single byte, not {:?}", s)),
+        };
+        let field = match args.value("key") {
+            None => None,
+            Some(k) => match k.parse::<usize>() {
+                Ok(k) if k > 0 => Some(k - 1),
+                _ => {
+                    return usage(format!(
+                        "key column must be a positive integer, not {:?}",
+                        k
+                    ))
+                }
+            },
+        };
+        Ok(KeySpec {
+            order,
+            separator,
+            field,
+        })
+    }
+
+    /// Return the key of `line`, which is empty if the line has too few fields.
+    pub fn key<'a>(&self, line: &'a [u8]) -> &'a [u8] {
+        let field = match self.field {
+            Some(field) => field,
+            None => return line,
+        };
+        let found = match self.separator {
+            Some(sep) => line.split(|&b| b == sep).nth(field),
+            None => line
+                .split(|b| b.is_ascii_whitespace())
+                .filter(|f| !f.is_empty())
+                .nth(field),
+        };
+        found.unwrap_or(&[])
+    }
+
+    /// Compare two keys.
+    pub fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        match self.order {
+            Order::Lexicographic => a.cmp(b),
+            Order::Numeric => match (number(a), number(b)) {
+                (Some(x), Some(y)) => x.total_cmp(&y),
+                (x, y) => x.is_some().cmp(&y.is_some()),
+            },
+            Order::Version => compare::natural(a, b),
+        }
+    }
+}
+
+fn number(key: &[u8]) -> Option<f64> {
+    std::str::from_utf8(key).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(args: &[&str]) -> KeySpec {
+        let args = Args::parse(args.iter().map(|a| a.to_string()), OPTS).unwrap();
+        KeySpec::from_args(&args, Order::Lexicographic).unwrap()
+    }
+
+    #[test]
+    fn keys() {
+        let line = b"  alice   30 x";
+        assert_eq!(spec(&[]).key(line), line);
+        assert_eq!(spec(&["-k", "2"]).key(line), b"30");
+        assert_eq!(spec(&["-k", "9"]).key(line), b"");
+        assert_eq!(spec(&["-t", ",", "-k2"]).key(b"a,b,c"), b"b");
+    }
+
+    #[test]
+    fn orders() {
+        let numeric = spec(&["-n"]);
+        assert_eq!(numeric.compare(b"9", b"10"), Ordering::Less);
+        assert_eq!(numeric.compare(b"x", b"-1e9"), Ordering::Less);
+        assert_eq!(numeric.compare(b"1.50", b"1.5"), Ordering::Equal);
+
+        let version = spec(&["--order", "version"]);
+        assert_eq!(version.compare(b"1.9", b"1.10"), Ordering::Less);
+        assert_eq!(version.compare(b"1.02", b"1.2"), Ordering::Equal);
+        assert_eq!(version.compare(b"1.2", b"1.2.1"), Ordering::Less);
+        assert_eq!(version.compare(b"v2", b"v10"), Ordering::Less);
+        assert_eq!(spec(&["--version-sort"]), version);
+        assert_eq!(spec(&["-V"]), version);
+
+        assert_eq!(spec(&[]).compare(b"9", b"10"), Ordering::Greater);
+    }
+
+    #[test]
+    fn invalid() {
+        let parse = |args: &[&str]| {
+            let args = Args::parse(args.iter().map(|a| a.to_string()), OPTS).unwrap();
+            KeySpec::from_args(&args, Order::Lexicographic)
+        };
+        assert!(parse(&["-n", "-V"]).is_err());
+        assert!(parse(&["--order", "random"]).is_err());
+        assert!(parse(&["-k", "0"]).is_err());
+        assert!(parse(&["-t", "ab"]).is_err());
+    }
+}
@@ -0,0 +1,306 @@
+//! The `run` command: find the first bad value by running a command on probes.
+
+use crate::args::{usage, Args, Error};
+use bisection::bisector::{Outcome, Session, Verdict};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Exit status of a probe command meaning the value cannot be tested, as for `git bisect run`.
+const SKIP: i32 = 125;
+
+/// How often to check whether a probe with a timeout has finished.
+const POLL: Duration = Duration::from_millis(10);
+
+/// Ordered values searched by `run`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Domain {
+    /// Consecutive integers starting at `start`.
+    Integers { start: i64, len: usize },
+    /// Values listed one per line in a file.
+    Candidates(Vec<String>),
+}
+
+impl Domain {
+    /// Parse a range such as `1..100` or `1..=100`.
+    pub fn parse_range(s: &str) -> Result<Self, Error> {
+        let invalid = || usage(format!("invalid range {:?}, expected A..B or A..=B", s));
+        let (start, end, inclusive) = match s.find("..") {
+            Some(i) if s[i + 2..].starts_with('=') => (&s[..i], &s[i + 3..], true),
+            Some(i) => (&s[..i], &s[i + 2..], false),
+            None => return invalid(),
+        };
+        let (start, end) = match (start.parse::<i64>(), end.parse::<i64>()) {
+            (Ok(start), Ok(end)) => (start, end),
+            _ => return invalid(),
+        };
+        let len = (end as i128 - start as i128 + inclusive as i128).max(0);
+        if len > usize::MAX as i128 {
+            return invalid();
+        }
+        Ok(Domain::Integers {
+            start,
+            len: len as usize,
+        })
+    }
+
+    /// Read non-empty lines of `path` as the candidates.
+    pub fn read_candidates(path: &str) -> Result<Self, Error> {
+        let mut candidates = vec![];
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            let line = line.trim();
+            if !line.is_empty() {
+                candidates.push(line.to_string());
+            }
+        }
+        Ok(Domain::Candidates(candidates))
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Domain::Integers { len, .. } => *len,
+            Domain::Candidates(c) => c.len(),
+        }
+    }
+
+    pub fn value(&self, index: usize) -> String {
+        match self {
+            Domain::Integers { start, .. } => (*start as i128 + index as i128).to_string(),
+            Domain::Candidates(c) => c[index].clone(),
+        }
+    }
+
+    /// Describe the values, for checking that a log is resumed with the same ones.
+    pub fn describe(&self) -> String {
+        match self {
+            Domain::Integers { start, len } => {
+                format!("range {}..{}", start, *start as i128 + *len as i128)
+            }
+            Domain::Candidates(c) => format!("candidates {} {:016x}", c.len(), fnv1a(c)),
+        }
+    }
+}
+
+/// FNV-1a hash of the candidates, each followed by a newline.
+fn fnv1a(candidates: &[String]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in candidates.iter().flat_map(|c| c.bytes().chain(Some(b'\n'))) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+pub fn run(args: &Args) -> Result<i32, Error> {
+    args.expect_positional(&[])?;
+    let domain = match (args.value("range"), args.value("candidates")) {
+        (Some(range), None) => Domain::parse_range(range)?,
+        (None, Some(path)) => Domain::read_candidates(path)?,
+        _ => return usage("expected one of --range or --candidates"),
+    };
+    let timeout = match args.value("timeout") {
+        None => None,
+        Some(t) => match t.parse::<f64>() {
+            Ok(secs) if secs > 0.0 && secs.is_finite() => Some(Duration::from_secs_f64(secs)),
+            _ => return usage(format!("invalid timeout {:?}", t)),
+        },
+    };
+    if args.trailing.is_empty() {
+        return usage("expected a command after --");
+    }
+    let log = args.value("log").map(Path::new);
+
+    match search(&domain, &args.trailing, timeout, log)? {
+        Outcome::Found(i) if i < domain.len() => {
+            println!("{}", domain.value(i));
+            Ok(0)
+        }
+        Outcome::Found(_) => {
+            eprintln!("bisection: no bad value found");
+            Ok(1)
+        }
+        Outcome::Ambiguous(candidates) => {
+            eprintln!("bisection: too many skipped values, the first bad value is one of:");
+            for i in candidates.filter(|&i| i < domain.len()) {
+                println!("{}", domain.value(i));
+            }
+            Ok(1)
+        }
+    }
+}
+
+/// Bisect `domain` with `command`, resuming from and saving progress to `log` if given.
+pub fn search(
+    domain: &Domain,
+    command: &[String],
+    timeout: Option<Duration>,
+    log: Option<&Path>,
+) -> Result<Outcome, Error> {
+    let mut session = match log {
+        Some(path) if path.exists() => {
+            let text = fs::read_to_string(path)?;
+            let session = Session::read_from(text.as_bytes())?;
+            if text.lines().next() != Some(&header(domain)) || session.range() != (0..domain.len())
+            {
+                return usage(format!("log {} is for different values", path.display()));
+            }
+            for step in session.steps() {
+                eprintln!(
+                    "bisection: {} {} (from log)",
+                    step.verdict,
+                    domain.value(step.index)
+                );
+            }
+            session
+        }
+        _ => Session::new(0..domain.len()),
+    };
+
+    while let Some(i) = session.next_probe() {
+        let value = domain.value(i);
+        let verdict = probe(command, &value, timeout)?;
+        eprintln!("bisection: {} {}", verdict, value);
+        session.mark(i, verdict);
+        if let Some(path) = log {
+            save(&session, domain, path)?;
+        }
+    }
+    Ok(session.outcome())
+}
+
+/// Return the comment line starting a log, recording the values searched.
+fn header(domain: &Domain) -> String {
+    format!("# {}", domain.describe())
+}
+
+/// Write the session log to a temporary file and move it into place, so an interrupted run
+/// never leaves a truncated log.
+fn save(session: &Session, domain: &Domain, path: &Path) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut buf = format!("{}\n", header(domain)).into_bytes();
+    session.write_to(&mut buf)?;
+    fs::write(&tmp, buf)?;
+    fs::rename(&tmp, path)
+}
+
+/// Run `command` with `{}` in its arguments replaced by `value`, or with `value` appended if
+/// there is no `{}`, and interpret its exit status.
+///
+/// Exit status 0 is good, 125 is skip, and other statuses up to 127 are bad. Commands which
+/// run longer than `timeout` are killed and count as bad. Other statuses, including death by
+/// a signal, abort the search.
+pub fn probe(command: &[String], value: &str, timeout: Option<Duration>) -> Result<Verdict, Error> {
+    let mut args: Vec<String> = command.iter().map(|a| a.replace("{}", value)).collect();
+    if !command.iter().any(|a| a.contains("{}")) {
+        args.push(value.to_string());
+    }
+    let mut child = Command::new(&args[0])
+        .args(&args[1..])
+        .stdin(Stdio::null())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot run {}: {}", args[0], e)))?;
+
+    let status = match timeout {
+        None => child.wait()?,
+        Some(timeout) => {
+            let deadline = Instant::now() + timeout;
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
+                if Instant::now() >= deadline {
+                    child.kill()?;
+                    child.wait()?;
+                    return Ok(Verdict::Bad);
+                }
+                thread::sleep(POLL);
+            }
+        }
+    };
+    verdict(status, &args[0])
+}
+
+fn verdict(status: ExitStatus, program: &str) -> Result<Verdict, Error> {
+    match status.code() {
+        Some(0) => Ok(Verdict::Good),
+        Some(SKIP) => Ok(Verdict::Skip),
+        Some(1..=127) => Ok(Verdict::Bad),
+        _ => Err(io::Error::other(format!("{} exited with {}, aborting", program, status)).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str) -> Vec<String> {
+        vec!["sh".to_string(), "-c".to_string(), script.to_string()]
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(
+            Domain::parse_range("1..=5000").unwrap(),
+            Domain::Integers {
+                start: 1,
+                len: 5000
+            }
+        );
+        assert_eq!(Domain::parse_range("-3..3").unwrap().value(0), "-3");
+        assert_eq!(Domain::parse_range("5..1").unwrap().len(), 0);
+        assert!(Domain::parse_range("1-5").is_err());
+        assert!(Domain::parse_range("a..=b").is_err());
+    }
+
+    #[test]
+    fn verdicts() {
+        assert_eq!(probe(&sh("exit 0"), "1", None).unwrap(), Verdict::Good);
+        assert_eq!(probe(&sh("exit 125"), "1", None).unwrap(), Verdict::Skip);
+        assert_eq!(probe(&sh("exit 3"), "1", None).unwrap(), Verdict::Bad);
+        assert!(probe(&sh("exit 200"), "1", None).is_err());
+        assert!(probe(&["/nonexistent/command".to_string()], "1", None).is_err());
+
+        let timeout = Some(Duration::from_millis(50));
+        assert_eq!(probe(&sh("sleep 5"), "1", timeout).unwrap(), Verdict::Bad);
+    }
+
+    #[test]
+    fn search_and_resume() {
+        let domain = Domain::parse_range("1..=5000").unwrap();
+        let command = sh("test {} -lt 1234");
+        let outcome = search(&domain, &command, None, None).unwrap();
+        assert_eq!(outcome, Outcome::Found(1233));
+
+        let dir = std::env::temp_dir().join(format!("bisection-run-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("log");
+        let logged = "# range 1..5001\nstart 0 5000\ngood 2499\nbad 3749\n";
+        fs::write(&log, logged).unwrap();
+        // Resuming skips the values already tested: the command would fail for them.
+        let command = sh("test {} -ne 2500 && test {} -ne 3750 && test {} -lt 3000");
+        let outcome = search(&domain, &command, None, Some(&log)).unwrap();
+        assert_eq!(outcome, Outcome::Found(2999));
+        let saved = fs::read_to_string(&log).unwrap();
+        assert!(saved.starts_with(logged));
+
+        // A log is only resumed with the values it was written for.
+        let shifted = Domain::parse_range("2..=5001").unwrap();
+        assert!(search(&shifted, &command, None, Some(&log)).is_err());
+        let candidates = Domain::Candidates(vec!["a".into(), "b".into()]);
+        let log = dir.join("candidates");
+        let outcome = search(&candidates, &sh("test {} = a"), None, Some(&log)).unwrap();
+        assert_eq!(outcome, Outcome::Found(1));
+        let renamed = Domain::Candidates(vec!["a".into(), "c".into()]);
+        assert!(search(&renamed, &sh("test {} = a"), None, Some(&log)).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let domain = Domain::Candidates(vec!["v1".into(), "v2".into(), "v3".into()]);
+        let outcome = search(&domain, &sh("test {} != v3"), None, None).unwrap();
+        assert_eq!(outcome, Outcome::Found(2));
+    }
+}
@@ -0,0 +1,401 @@
+//! Bisection with expensive, possibly untestable predicates.
+//!
+//! This generalizes [`bisect_left_by`](crate::bisect_left_by) to the regression hunting workflow
+//! of `git bisect`: the predicate is evaluated on indices rather than elements, each evaluation
+//! may take minutes, and some points cannot be tested at all. Results are cached so no index is
+//! ever evaluated twice, untestable points are worked around by probing their neighbours, and if
+//! too many points are untestable the range of candidates that remains is reported instead.
+//!
+//! [`Bisector`] runs a search to completion with a closure, while [`Session`] exposes the same
+//! search one step at a time and can be saved to and restored from a text log.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::ops::{Range, RangeInclusive};
+use std::str::FromStr;
+
+/// Result of evaluating the predicate at an index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Verdict {
+    /// The index is before the boundary.
+    Good,
+    /// The index is at or after the boundary.
+    Bad,
+    /// The index cannot be tested.
+    Skip,
+}
+
+/// A single evaluation of the predicate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step {
+    /// Index at which the predicate was evaluated.
+    pub index: usize,
+    /// What the predicate reported for `index`.
+    pub verdict: Verdict,
+}
+
+/// Final result of a bisection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The first bad index, or the end of the range if every index is good.
+    Found(usize),
+    /// The first bad index is somewhere in this range but the indices that would tell which
+    /// were all skipped. The end of the range may be the end of the searched range, meaning
+    /// every index could be good.
+    Ambiguous(RangeInclusive<usize>),
+}
+
+/// State of a bisection driven one evaluation at a time.
+///
+/// A session proposes indices to evaluate with [`next_probe`](Session::next_probe) and is told
+/// the results with [`mark`](Session::mark), so the evaluations can be made by external tooling
+/// rather than a closure. Sessions can be written to a plain text log, in the spirit of
+/// `git bisect log`, and restored from it to resume after a restart.
+///
+/// Indices are assumed to be good up to some boundary and bad from there on, as with the
+/// `f(e) == Less` and `f(e) != Less` halves of a slice searched by `bisect_left_by`.
+#[derive(Clone, Debug)]
+pub struct Session {
+    range: Range<usize>,
+    /// Every index before `lo` is known to be good.
+    lo: usize,
+    /// `hi` is the first index known to be bad, or the end of the range.
+    hi: usize,
+    cache: HashMap<usize, Verdict>,
+    steps: Vec<Step>,
+}
+
+impl Session {
+    /// Start a session searching for the first bad index in `range`.
+    pub fn new(range: Range<usize>) -> Self {
+        let range = range.start..range.end.max(range.start);
+        Session {
+            lo: range.start,
+            hi: range.end,
+            range,
+            cache: HashMap::new(),
+            steps: vec![],
+        }
+    }
+
+    /// Return the range being searched.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Return the next index to evaluate, or `None` if no index left to test would narrow the
+    /// candidates further.
+    ///
+    /// The midpoint of the candidates is preferred, moving outwards to its nearest neighbours
+    /// if it was skipped.
+    pub fn next_probe(&self) -> Option<usize> {
+        let (lo, hi) = (self.lo, self.hi);
+        let mid = lo + (hi - lo) / 2;
+        for d in 0..hi - lo {
+            for &i in [mid.checked_add(d), mid.checked_sub(d + 1)]
+                .iter()
+                .flatten()
+            {
+                if lo <= i && i < hi && self.cache.get(&i) != Some(&Verdict::Skip) {
+                    return Some(i);
+                }
+            }
+        }
+        None
+    }
+
+    /// Record the verdict for `index`, narrowing the candidates.
+    ///
+    /// Any index may be marked, not only the one returned by `next_probe`. Verdicts
+    /// contradicting earlier ones are recorded but do not move the candidates.
+    pub fn mark(&mut self, index: usize, verdict: Verdict) {
+        self.cache.insert(index, verdict);
+        self.steps.push(Step { index, verdict });
+        match verdict {
+            Verdict::Good if index < self.hi => self.lo = self.lo.max(index + 1),
+            Verdict::Bad if index >= self.lo => self.hi = self.hi.min(index),
+            _ => {}
+        }
+    }
+
+    /// Return `true` if no further evaluation can narrow the candidates.
+    pub fn is_done(&self) -> bool {
+        self.next_probe().is_none()
+    }
+
+    /// Return the outcome given the evaluations made so far.
+    pub fn outcome(&self) -> Outcome {
+        if self.lo == self.hi {
+            Outcome::Found(self.lo)
+        } else {
+            Outcome::Ambiguous(self.candidates())
+        }
+    }
+
+    /// Return the range of indices which may still be the first bad index.
+    pub fn candidates(&self) -> RangeInclusive<usize> {
+        self.lo..=self.hi
+    }
+
+    /// Return the evaluations made so far, in order.
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Return the verdict recorded for `index`, if any.
+    pub fn verdict(&self, index: usize) -> Option<Verdict> {
+        self.cache.get(&index).copied()
+    }
+
+    /// Write the session to `w` as a text log.
+    ///
+    /// The log starts with a `start <lo> <hi>` line giving the searched range, followed by a
+    /// `good <index>`, `bad <index>` or `skip <index>` line for each step.
+    pub fn write_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "start {} {}", self.range.start, self.range.end)?;
+        for step in &self.steps {
+            writeln!(w, "{} {}", step.verdict, step.index)?;
+        }
+        Ok(())
+    }
+
+    /// Restore a session by replaying a log written by [`write_to`](Session::write_to).
+    ///
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn read_from<R: BufRead>(r: R) -> io::Result<Self> {
+        let mut session: Option<Session> = None;
+        for (n, line) in r.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid bisection log line {}: {:?}", n + 1, line),
+                )
+            };
+            let words: Vec<_> = line.split_whitespace().collect();
+            match (&mut session, &words[..]) {
+                (None, ["start", lo, hi]) => {
+                    let lo = lo.parse().map_err(|_| invalid())?;
+                    let hi = hi.parse().map_err(|_| invalid())?;
+                    session = Some(Session::new(lo..hi));
+                }
+                (Some(session), [verdict, index]) => {
+                    let verdict = verdict.parse().map_err(|_| invalid())?;
+                    let index = index.parse().map_err(|_| invalid())?;
+                    session.mark(index, verdict);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        session.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "empty bisection log"))
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Verdict::Good => "good",
+            Verdict::Bad => "bad",
+            Verdict::Skip => "skip",
+        })
+    }
+}
+
+impl FromStr for Verdict {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "good" => Ok(Verdict::Good),
+            "bad" => Ok(Verdict::Bad),
+            "skip" => Ok(Verdict::Skip),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Bisection driven by a predicate returning [`Verdict`]s.
+#[derive(Clone, Debug)]
+pub struct Bisector {
+    session: Session,
+}
+
+impl Bisector {
+    /// Create a bisector searching for the first bad index in `range`.
+    pub fn new(range: Range<usize>) -> Self {
+        Bisector {
+            session: Session::new(range),
+        }
+    }
+
+    /// Run the bisection to completion, evaluating `f` at each probed index.
+    ///
+    /// Indices whose verdict is already known are never evaluated again, so `run` can be called
+    /// repeatedly, for example after a panic in `f`, without repeating work.
+    pub fn run<F>(&mut self, mut f: F) -> Outcome
+    where
+        F: FnMut(usize) -> Verdict,
+    {
+        while let Some(index) = self.session.next_probe() {
+            let verdict = f(index);
+            self.session.mark(index, verdict);
+        }
+        self.session.outcome()
+    }
+
+    /// Return the evaluations made so far, in order.
+    pub fn steps(&self) -> &[Step] {
+        self.session.steps()
+    }
+
+    /// Return the cached verdict for `index`, if it has been evaluated.
+    pub fn verdict(&self, index: usize) -> Option<Verdict> {
+        self.session.verdict(index)
+    }
+
+    /// Return the range of indices which may still be the first bad index.
+    pub fn candidates(&self) -> RangeInclusive<usize> {
+        self.session.candidates()
+    }
+
+    /// Return the underlying session.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+}
+
+impl From<Session> for Bisector {
+    /// Continue a session, such as one restored from a log, with a predicate.
+    fn from(session: Session) -> Self {
+        Bisector { session }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bisect_left_by;
+    use proptest::prelude::*;
+
+    fn verdict(boundary: usize) -> impl Fn(usize) -> Verdict {
+        move |i| {
+            if i < boundary {
+                Verdict::Good
+            } else {
+                Verdict::Bad
+            }
+        }
+    }
+
+    #[test]
+    fn empty_range() {
+        let mut bisector = Bisector::new(3..3);
+        assert_eq!(bisector.run(|_| unreachable!()), Outcome::Found(3));
+    }
+
+    #[test]
+    fn works_around_skips() {
+        let mut bisector = Bisector::new(0..100);
+        let f = verdict(42);
+        let outcome = bisector.run(|i| if i % 2 == 0 { Verdict::Skip } else { f(i) });
+        // 41 and 43 are testable, 42 is not: the boundary is either 42 or 43.
+        assert_eq!(outcome, Outcome::Ambiguous(42..=43));
+
+        let mut bisector = Bisector::new(0..100);
+        let f = verdict(43);
+        let outcome = bisector.run(|i| if i % 10 == 0 { Verdict::Skip } else { f(i) });
+        assert_eq!(outcome, Outcome::Found(43));
+        assert_eq!(bisector.steps()[0].verdict, Verdict::Skip);
+    }
+
+    #[test]
+    fn all_skipped() {
+        let mut bisector = Bisector::new(10..20);
+        assert_eq!(bisector.run(|_| Verdict::Skip), Outcome::Ambiguous(10..=20));
+        assert_eq!(bisector.steps().len(), 10);
+    }
+
+    #[test]
+    fn never_evaluates_twice() {
+        let mut bisector = Bisector::new(0..1000);
+        let mut calls = HashMap::new();
+        let f = verdict(617);
+        bisector.run(|i| {
+            *calls.entry(i).or_insert(0) += 1;
+            if i % 3 == 0 {
+                Verdict::Skip
+            } else {
+                f(i)
+            }
+        });
+        assert!(calls.values().all(|&n| n == 1));
+        assert_eq!(calls.len(), bisector.steps().len());
+    }
+
+    #[test]
+    fn session_driven_externally() {
+        let f = verdict(30);
+        let mut session = Session::new(0..64);
+        while let Some(i) = session.next_probe() {
+            session.mark(i, f(i));
+        }
+        assert!(session.is_done());
+        assert_eq!(session.outcome(), Outcome::Found(30));
+    }
+
+    #[test]
+    fn session_log_round_trip() {
+        let f = verdict(700);
+        let mut session = Session::new(0..1000);
+        for _ in 0..4 {
+            let i = session.next_probe().unwrap();
+            session.mark(i, if i == 500 { Verdict::Skip } else { f(i) });
+        }
+
+        let mut log = vec![];
+        session.write_to(&mut log).unwrap();
+        assert_eq!(
+            String::from_utf8(log.clone()).unwrap(),
+            "start 0 1000\nskip 500\ngood 499\nbad 750\ngood 625\n"
+        );
+
+        let restored = Session::read_from(&log[..]).unwrap();
+        assert_eq!(restored.steps(), session.steps());
+        assert_eq!(restored.next_probe(), session.next_probe());
+
+        let mut bisector = Bisector::from(restored);
+        assert_eq!(bisector.run(f), Outcome::Found(700));
+    }
+
+    #[test]
+    fn invalid_log() {
+        assert!(Session::read_from(&b""[..]).is_err());
+        assert!(Session::read_from(&b"good 3\n"[..]).is_err());
+        assert!(Session::read_from(&b"start 0 10\nmaybe 3\n"[..]).is_err());
+        assert!(Session::read_from(&b"# comment\nstart 0 10\n\nbad 3\n"[..]).is_ok());
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_bisect_left_by(
+            mut flags in prop::collection::vec(any::<bool>(), 0..200),
+        ) {
+            flags.sort();
+
+            let expected = bisect_left_by(&flags, |&bad| if bad {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Less
+            });
+            let mut bisector = Bisector::new(0..flags.len());
+            let outcome = bisector.run(|i| if flags[i] { Verdict::Bad } else { Verdict::Good });
+
+            prop_assert_eq!(outcome, Outcome::Found(expected));
+        }
+    }
+}
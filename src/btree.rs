@@ -0,0 +1,333 @@
+//! Static B+tree (S-tree) search index over sorted integer keys.
+//!
+//! Keys are laid out as an implicit `B + 1`-ary search tree whose nodes hold `B` keys each and
+//! are aligned to cache lines. A lookup reads a single node per level and ranks the query against
+//! all of its keys at once, using AVX2 on `x86_64` when the CPU supports it and a portable scalar
+//! loop otherwise.
+
+use std::fmt::Debug;
+
+/// Number of keys per node. Sixteen 32-bit keys fill exactly one 64-byte cache line.
+const B: usize = 16;
+
+/// Primitive integer keys supported by [`StaticBTreeIndex`].
+pub trait Key: Copy + Ord + Debug + private::Sealed {}
+
+mod private {
+    use super::B;
+
+    pub trait Sealed: Sized {
+        /// Largest value of the type, used to pad the final nodes.
+        const MAX: Self;
+
+        /// Return the smallest value greater than `self`, if any.
+        fn successor(self) -> Option<Self>;
+
+        /// Return the number of `keys` less than `x`.
+        fn rank(keys: &[Self; B], x: Self, simd: bool) -> usize;
+    }
+}
+
+use private::Sealed;
+
+fn scalar_rank<T: Ord>(keys: &[T; B], x: &T) -> usize {
+    keys.iter().map(|k| (k < x) as usize).sum()
+}
+
+macro_rules! scalar_key {
+    ($($t:ty),*) => {
+        $(
+            impl Key for $t {}
+
+            impl Sealed for $t {
+                const MAX: Self = <$t>::MAX;
+
+                fn successor(self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+
+                fn rank(keys: &[Self; B], x: Self, _simd: bool) -> usize {
+                    scalar_rank(keys, &x)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! simd_key {
+    ($($t:ty => $rank:ident),*) => {
+        $(
+            impl Key for $t {}
+
+            impl Sealed for $t {
+                const MAX: Self = <$t>::MAX;
+
+                fn successor(self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+
+                fn rank(keys: &[Self; B], x: Self, simd: bool) -> usize {
+                    #[cfg(target_arch = "x86_64")]
+                    {
+                        if simd {
+                            // SAFETY: `simd` is only set when AVX2 was detected at runtime.
+                            return unsafe { x86::$rank(keys, x) };
+                        }
+                    }
+                    #[cfg(not(target_arch = "x86_64"))]
+                    let _ = simd;
+                    scalar_rank(keys, &x)
+                }
+            }
+        )*
+    };
+}
+
+scalar_key!(u8, u16, usize, i8, i16, isize);
+simd_key!(u32 => rank_u32, u64 => rank_u64, i32 => rank_i32, i64 => rank_i64);
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::B;
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn count_gt_epi32(keys: *const __m256i, x: __m256i, flip: __m256i) -> usize {
+        let mut count = 0;
+        for i in 0..B / 8 {
+            let k = _mm256_xor_si256(_mm256_loadu_si256(keys.add(i)), flip);
+            let lt = _mm256_cmpgt_epi32(x, k);
+            count += _mm256_movemask_ps(_mm256_castsi256_ps(lt)).count_ones();
+        }
+        count as usize
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn count_gt_epi64(keys: *const __m256i, x: __m256i, flip: __m256i) -> usize {
+        let mut count = 0;
+        for i in 0..B / 4 {
+            let k = _mm256_xor_si256(_mm256_loadu_si256(keys.add(i)), flip);
+            let lt = _mm256_cmpgt_epi64(x, k);
+            count += _mm256_movemask_pd(_mm256_castsi256_pd(lt)).count_ones();
+        }
+        count as usize
+    }
+
+    // AVX2 only has signed comparisons, so unsigned keys have their sign bit flipped first.
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn rank_i32(keys: &[i32; B], x: i32) -> usize {
+        let flip = _mm256_setzero_si256();
+        count_gt_epi32(keys.as_ptr() as *const __m256i, _mm256_set1_epi32(x), flip)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn rank_u32(keys: &[u32; B], x: u32) -> usize {
+        let flip = _mm256_set1_epi32(i32::MIN);
+        let x = _mm256_set1_epi32((x as i32) ^ i32::MIN);
+        count_gt_epi32(keys.as_ptr() as *const __m256i, x, flip)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn rank_i64(keys: &[i64; B], x: i64) -> usize {
+        let flip = _mm256_setzero_si256();
+        count_gt_epi64(keys.as_ptr() as *const __m256i, _mm256_set1_epi64x(x), flip)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn rank_u64(keys: &[u64; B], x: u64) -> usize {
+        let flip = _mm256_set1_epi64x(i64::MIN);
+        let x = _mm256_set1_epi64x((x as i64) ^ i64::MIN);
+        count_gt_epi64(keys.as_ptr() as *const __m256i, x, flip)
+    }
+}
+
+#[repr(align(64))]
+#[derive(Clone, Debug)]
+struct Node<T>([T; B]);
+
+/// Read-only index over a sorted slice of integers answering `bisect_left`/`bisect_right`
+/// queries.
+///
+/// As with [`EytzingerIndex`](crate::eytzinger::EytzingerIndex), returned indices refer to
+/// positions in the original sorted slice.
+#[derive(Clone, Debug)]
+pub struct StaticBTreeIndex<T> {
+    nodes: Vec<Node<T>>,
+    /// `ranks[k * B + i]` is the index in the sorted slice of `nodes[k].0[i]`, or `len` for
+    /// padding.
+    ranks: Vec<usize>,
+    len: usize,
+    simd: bool,
+}
+
+impl<T: Key> StaticBTreeIndex<T> {
+    /// Build an index from `sorted`, assuming it is sorted.
+    ///
+    /// Lookups use SIMD instructions if they are supported by the running CPU.
+    pub fn new(sorted: &[T]) -> Self {
+        Self::build(sorted, simd_supported())
+    }
+
+    /// Build an index from `sorted`, assuming it is sorted, which only uses the portable scalar
+    /// implementation for lookups.
+    pub fn new_scalar(sorted: &[T]) -> Self {
+        Self::build(sorted, false)
+    }
+
+    fn build(sorted: &[T], simd: bool) -> Self {
+        let len = sorted.len();
+        let blocks = len.div_ceil(B);
+        let mut ranks = vec![len; blocks * B];
+        let mut next = 0;
+        fill_ranks(&mut ranks, blocks, len, &mut next, 0);
+
+        let nodes = ranks
+            .chunks_exact(B)
+            .map(|chunk| {
+                let mut keys = [T::MAX; B];
+                for (key, &r) in keys.iter_mut().zip(chunk) {
+                    if r < len {
+                        *key = sorted[r];
+                    }
+                }
+                Node(keys)
+            })
+            .collect();
+
+        StaticBTreeIndex {
+            nodes,
+            ranks,
+            len,
+            simd,
+        }
+    }
+
+    /// Return the number of keys in the index.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return `true` if the index contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return the index where `x` should be inserted in the original sorted slice.
+    ///
+    /// Equivalent to [`bisect_left`](crate::bisect_left) on the slice the index was built from.
+    pub fn bisect_left(&self, x: T) -> usize {
+        let mut best = None;
+        let mut k = 0;
+        while k < self.nodes.len() {
+            let i = T::rank(&self.nodes[k].0, x, self.simd);
+            if i < B {
+                best = Some(k * B + i);
+            }
+            k = k * (B + 1) + i + 1;
+        }
+        best.map_or(self.len, |slot| self.ranks[slot])
+    }
+
+    /// Return the index where `x` should be inserted in the original sorted slice.
+    ///
+    /// Equivalent to [`bisect_right`](crate::bisect_right) on the slice the index was built
+    /// from.
+    pub fn bisect_right(&self, x: T) -> usize {
+        match x.successor() {
+            Some(next) => self.bisect_left(next),
+            None => self.len,
+        }
+    }
+}
+
+/// Assign sorted indices to the slots of the subtree rooted at node `k` by an in-order
+/// traversal.
+fn fill_ranks(ranks: &mut [usize], blocks: usize, len: usize, next: &mut usize, k: usize) {
+    if k < blocks {
+        for i in 0..B {
+            fill_ranks(ranks, blocks, len, next, k * (B + 1) + i + 1);
+            if *next < len {
+                ranks[k * B + i] = *next;
+                *next += 1;
+            }
+        }
+        fill_ranks(ranks, blocks, len, next, k * (B + 1) + B + 1);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn simd_supported() -> bool {
+    is_x86_feature_detected!("avx2")
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn simd_supported() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bisect_left, bisect_right};
+    use proptest::prelude::*;
+
+    fn check<T: Key>(a: &[T], x: T) {
+        for index in &[StaticBTreeIndex::new(a), StaticBTreeIndex::new_scalar(a)] {
+            assert_eq!(index.len(), a.len());
+            assert_eq!(index.bisect_left(x), bisect_left(a, &x));
+            assert_eq!(index.bisect_right(x), bisect_right(a, &x));
+        }
+    }
+
+    #[test]
+    fn empty() {
+        check::<u32>(&[], 0);
+        check::<u32>(&[], u32::MAX);
+    }
+
+    #[test]
+    fn extreme_keys() {
+        let a: Vec<u64> = vec![0, 0, 1, u64::MAX - 1, u64::MAX, u64::MAX];
+        for &x in &[0, 1, 2, u64::MAX - 1, u64::MAX] {
+            check(&a, x);
+        }
+
+        let a: Vec<i32> = vec![i32::MIN, -1, 0, 0, 1, i32::MAX];
+        for &x in &[i32::MIN, -2, -1, 0, 1, 2, i32::MAX] {
+            check(&a, x);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_u32_matches_bisect(
+            mut nums in prop::collection::vec(any::<u32>(), 0..1000),
+            num in any::<u32>()
+        ) {
+            nums.sort();
+            check(&nums, num);
+            if let Some(&existing) = nums.first() {
+                check(&nums, existing);
+            }
+        }
+
+        #[test]
+        fn test_i64_matches_bisect(
+            mut nums in prop::collection::vec(-500..500_i64, 0..1000),
+            num in -600..600_i64
+        ) {
+            nums.sort();
+            check(&nums, num);
+        }
+
+        #[test]
+        fn test_u8_matches_bisect(
+            mut nums in prop::collection::vec(any::<u8>(), 0..300),
+            num in any::<u8>()
+        ) {
+            nums.sort();
+            check(&nums, num);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Bisection over sorted fixed-width numbers stored as raw bytes.
+//!
+//! Columnar files, memory maps and network buffers often hold sorted numbers as packed bytes in
+//! a fixed byte order, with no alignment guarantees. A [`Column`] views such bytes as a sorted
+//! sequence of numbers, decoding only the elements each search probes, so the data never needs
+//! to be copied into a `Vec` to be searched.
+
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::mem;
+
+/// Byte order of the numbers in a [`Column`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    /// Byte order of the target platform.
+    #[cfg(target_endian = "little")]
+    pub const NATIVE: Endian = Endian::Little;
+    /// Byte order of the target platform.
+    #[cfg(target_endian = "big")]
+    pub const NATIVE: Endian = Endian::Big;
+}
+
+/// Primitive numbers which can be decoded from a [`Column`].
+///
+/// Floats are ordered by `total_cmp`, so negative zero sorts before zero and `NaN`s sort at the
+/// ends, as produced by sorting with `total_cmp`.
+pub trait FixedWidth: Copy + Debug + private::Sealed {}
+
+mod private {
+    use std::cmp::Ordering;
+
+    pub trait Sealed: Sized {
+        /// Decode a value from exactly `size_of::<Self>()` bytes.
+        fn decode(bytes: &[u8], endian: super::Endian) -> Self;
+
+        /// Compare two values under a total order.
+        fn total_cmp(&self, other: &Self) -> Ordering;
+    }
+}
+
+macro_rules! fixed_width {
+    ($($t:ty: |$a:ident, $b:ident| $cmp:expr),* $(,)?) => {
+        $(
+            impl FixedWidth for $t {}
+
+            impl private::Sealed for $t {
+                fn decode(bytes: &[u8], endian: Endian) -> Self {
+                    let mut buf = [0; mem::size_of::<$t>()];
+                    buf.copy_from_slice(bytes);
+                    match endian {
+                        Endian::Little => <$t>::from_le_bytes(buf),
+                        Endian::Big => <$t>::from_be_bytes(buf),
+                    }
+                }
+
+                fn total_cmp(&self, other: &Self) -> Ordering {
+                    let ($a, $b) = (self, other);
+                    $cmp
+                }
+            }
+        )*
+    };
+}
+
+fixed_width!(
+    u8: |a, b| a.cmp(b),
+    u16: |a, b| a.cmp(b),
+    u32: |a, b| a.cmp(b),
+    u64: |a, b| a.cmp(b),
+    u128: |a, b| a.cmp(b),
+    i8: |a, b| a.cmp(b),
+    i16: |a, b| a.cmp(b),
+    i32: |a, b| a.cmp(b),
+    i64: |a, b| a.cmp(b),
+    i128: |a, b| a.cmp(b),
+    f32: |a, b| a.total_cmp(b),
+    f64: |a, b| a.total_cmp(b),
+);
+
+/// View of bytes as a sequence of numbers of type `T` in a given byte order.
+#[derive(Clone, Copy, Debug)]
+pub struct Column<'a, T> {
+    data: &'a [u8],
+    endian: Endian,
+    marker: PhantomData<T>,
+}
+
+impl<'a, T: FixedWidth> Column<'a, T> {
+    /// View `data` as numbers of type `T` stored in `endian` byte order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `data` is not a multiple of the size of `T`.
+    pub fn new(data: &'a [u8], endian: Endian) -> Self {
+        assert_eq!(
+            data.len() % mem::size_of::<T>(),
+            0,
+            "length is not a multiple of the element size"
+        );
+        Column {
+            data,
+            endian,
+            marker: PhantomData,
+        }
+    }
+
+    /// Return the number of elements.
+    pub fn len(&self) -> usize {
+        self.data.len() / mem::size_of::<T>()
+    }
+
+    /// Return `true` if there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Return the element at `index`, or `None` if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> Option<T> {
+        let size = mem::size_of::<T>();
+        let start = index.checked_mul(size)?;
+        let bytes = self.data.get(start..start.checked_add(size)?)?;
+        Some(T::decode(bytes, self.endian))
+    }
+
+    /// Return an iterator over the elements.
+    pub fn iter(&self) -> impl Iterator<Item = T> + 'a {
+        let endian = self.endian;
+        self.data
+            .chunks_exact(mem::size_of::<T>())
+            .map(move |bytes| T::decode(bytes, endian))
+    }
+
+    /// Return the index where `x` should be inserted, assuming the elements are sorted.
+    ///
+    /// The return value `i` is such that all elements before `i` are less than `x`, and all
+    /// other elements are greater than or equal to `x`.
+    pub fn bisect_left(&self, x: &T) -> usize {
+        self.bisect_left_by(|e| e.total_cmp(x))
+    }
+
+    /// Return the index where `x` should be inserted, assuming the elements are sorted.
+    ///
+    /// The return value `i` is such that all elements before `i` are less than or equal to `x`,
+    /// and all other elements are greater than `x`.
+    pub fn bisect_right(&self, x: &T) -> usize {
+        self.bisect_right_by(|e| x.total_cmp(&e))
+    }
+
+    /// Return the index where a value should be inserted, assuming the elements are sorted,
+    /// according to a comparator function.
+    ///
+    /// The comparator function follows the same contract as
+    /// [`bisect_left_by`](crate::bisect_left_by).
+    pub fn bisect_left_by<F>(&self, mut f: F) -> usize
+    where
+        F: FnMut(T) -> Ordering,
+    {
+        self.partition_point(|e| f(e) == Ordering::Less)
+    }
+
+    /// Return the index where a value should be inserted, assuming the elements are sorted,
+    /// according to a comparator function.
+    ///
+    /// The comparator function follows the same contract as
+    /// [`bisect_right_by`](crate::bisect_right_by).
+    pub fn bisect_right_by<F>(&self, mut f: F) -> usize
+    where
+        F: FnMut(T) -> Ordering,
+    {
+        self.partition_point(|e| f(e) != Ordering::Less)
+    }
+
+    fn partition_point<P>(&self, mut before: P) -> usize
+    where
+        P: FnMut(T) -> bool,
+    {
+        let size = mem::size_of::<T>();
+        let (mut lo, mut hi) = (0, self.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let e = T::decode(&self.data[mid * size..(mid + 1) * size], self.endian);
+            if before(e) {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn unaligned() {
+        let keys = [3_u64, 5, 5, 9, 1 << 40];
+        // Offset by one byte so the `u64`s are not aligned.
+        let mut data = vec![0];
+        data.extend(keys.iter().flat_map(|k| k.to_be_bytes()));
+        let column = Column::<u64>::new(&data[1..], Endian::Big);
+
+        assert_eq!(column.len(), 5);
+        assert_eq!(column.get(4), Some(1 << 40));
+        assert_eq!(column.get(5), None);
+        assert_eq!(column.bisect_left(&5), 1);
+        assert_eq!(column.bisect_right(&5), 3);
+        assert_eq!(column.bisect_left(&(1 << 41)), 5);
+        assert!(column.iter().eq(keys.iter().copied()));
+    }
+
+    #[test]
+    fn floats() {
+        let values = [f64::NEG_INFINITY, -1.5, -0.0, 0.0, 2.5, f64::NAN];
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let column = Column::<f64>::new(&data, Endian::Little);
+
+        assert_eq!(column.bisect_left(&0.0), 3);
+        assert_eq!(column.bisect_right(&-0.0), 3);
+        assert_eq!(column.bisect_left(&f64::NAN), 5);
+        assert_eq!(column.bisect_left(&f64::INFINITY), 5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn ragged() {
+        Column::<u32>::new(&[0; 7], Endian::NATIVE);
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_bisect(
+            mut nums in prop::collection::vec(any::<i32>(), 0..200),
+            x in any::<i32>(),
+            big in any::<bool>()
+        ) {
+            nums.sort();
+            let (endian, data): (_, Vec<u8>) = if big {
+                (Endian::Big, nums.iter().flat_map(|n| n.to_be_bytes()).collect())
+            } else {
+                (Endian::Little, nums.iter().flat_map(|n| n.to_le_bytes()).collect())
+            };
+            let column = Column::<i32>::new(&data, endian);
+            prop_assert_eq!(column.bisect_left(&x), crate::bisect_left(&nums, &x));
+            prop_assert_eq!(column.bisect_right(&x), crate::bisect_right(&nums, &x));
+        }
+    }
+}
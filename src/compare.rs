@@ -0,0 +1,307 @@
+//! Ready-made comparators for strings which do not sort lexicographically.
+//!
+//! Every comparator takes two arguments, so it can be passed directly to `sort_by` and the
+//! `insort_*_by` functions, and used with the `bisect_*_by` functions by fixing one argument,
+//! as in `bisect_left_by(&files, |f| natural(f, "file10"))`.
+//!
+//! Each comparator is a total preorder: it is transitive, `cmp(a, b)` is always the reverse of
+//! `cmp(b, a)`, and `Equal` is an equivalence relation. This is exactly what sorting and
+//! bisection require, so a slice sorted with a comparator can be searched with the same
+//! comparator. Distinct strings may compare `Equal`, such as `"file01"` and `"file1"` under
+//! [`natural`], in which case they are treated as duplicates.
+
+use std::cmp::Ordering;
+
+/// Compare runs of ASCII digits by numeric value and everything else bytewise, so that
+/// `"file2" < "file10"` and `"1.9" < "1.10"`.
+///
+/// Digit runs may be arbitrarily long and leading zeros are ignored. Where one string has a
+/// digit and the other does not, the bytes themselves are compared.
+pub fn natural<A, B>(a: &A, b: &B) -> Ordering
+where
+    A: AsRef<[u8]> + ?Sized,
+    B: AsRef<[u8]> + ?Sized,
+{
+    let (mut a, mut b) = (a.as_ref(), b.as_ref());
+    loop {
+        let (x, y) = match (a.first(), b.first()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => (x, y),
+        };
+        let digits = x.is_ascii_digit();
+        if digits != y.is_ascii_digit() {
+            return x.cmp(y);
+        }
+        let run = |s: &[u8]| {
+            s.iter()
+                .take_while(|c| c.is_ascii_digit() == digits)
+                .count()
+        };
+        let (ra, rest_a) = a.split_at(run(a));
+        let (rb, rest_b) = b.split_at(run(b));
+        let ordering = if digits {
+            numeric_cmp(ra, rb)
+        } else {
+            ra.cmp(rb)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        a = rest_a;
+        b = rest_b;
+    }
+}
+
+/// Compare version strings following the precedence rules of semantic versioning, so that
+/// `"1.10.0" > "1.9.3"` and `"1.0.0-rc.1" < "1.0.0"`.
+///
+/// A leading `v` or `V` and build metadata after `+` are ignored. The dot-separated components
+/// of the version proper are compared in order, with missing components counting as `0`, so
+/// `"1.2" == "1.2.0"`. A version with a pre-release part after `-` is less than the same
+/// version without one, and pre-release parts are compared component by component, with the
+/// shorter one less if all its components are equal to the other's.
+///
+/// Components consisting only of digits are compared by numeric value and are less than other
+/// components, which are compared bytewise. Strings which are not valid versions are still
+/// ordered consistently.
+pub fn version<A, B>(a: &A, b: &B) -> Ordering
+where
+    A: AsRef<[u8]> + ?Sized,
+    B: AsRef<[u8]> + ?Sized,
+{
+    let (core_a, pre_a) = split_version(a.as_ref());
+    let (core_b, pre_b) = split_version(b.as_ref());
+    let (mut xs, mut ys) = (core_a.split(|&c| c == b'.'), core_b.split(|&c| c == b'.'));
+    loop {
+        let ordering = match (xs.next(), ys.next()) {
+            (None, None) => break,
+            (x, y) => identifier_cmp(x.unwrap_or(b"0"), y.unwrap_or(b"0")),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    match (pre_a, pre_b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(x), Some(y)) => dotted_numeric(x, y),
+    }
+}
+
+/// Compare dot-separated components in order, so that `"10.0.0.9" < "10.0.0.10"` and
+/// `"2.3" < "2.3.1"`.
+///
+/// Components consisting only of digits are compared by numeric value and are less than other
+/// components, which are compared bytewise. Unlike [`version`], a string whose components are
+/// all equal to the start of another's is less than it, so `"1.2" < "1.2.0"`.
+pub fn dotted_numeric<A, B>(a: &A, b: &B) -> Ordering
+where
+    A: AsRef<[u8]> + ?Sized,
+    B: AsRef<[u8]> + ?Sized,
+{
+    let (mut xs, mut ys) = (
+        a.as_ref().split(|&c| c == b'.'),
+        b.as_ref().split(|&c| c == b'.'),
+    );
+    loop {
+        let ordering = match (xs.next(), ys.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => identifier_cmp(x, y),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Compare bytewise after converting ASCII letters to lowercase, so that `"apple" < "Banana"`.
+///
+/// Other bytes, including non-ASCII UTF-8, are compared unchanged.
+pub fn ascii_case_insensitive<A, B>(a: &A, b: &B) -> Ordering
+where
+    A: AsRef<[u8]> + ?Sized,
+    B: AsRef<[u8]> + ?Sized,
+{
+    let (a, b) = (a.as_ref(), b.as_ref());
+    a.iter()
+        .map(u8::to_ascii_lowercase)
+        .cmp(b.iter().map(u8::to_ascii_lowercase))
+}
+
+/// Compare strings character by character after simple case folding, so that
+/// `"ÉCOLE" == "école"` and `"ΣΊΣΥΦΟΣ" == "σίσυφος"`.
+///
+/// Simple case folding maps each character to a single character and is independent of the
+/// locale. It is derived from the standard library's case mappings: a character is folded to
+/// the lowercase of its uppercase, where each is a single character, which agrees with the
+/// Unicode simple case folding for all but a few rare characters. Characters which only have
+/// multi-character mappings, such as `ß`, are left unchanged, so `"Straße"` and `"STRASSE"`
+/// differ.
+pub fn case_insensitive<A, B>(a: &A, b: &B) -> Ordering
+where
+    A: AsRef<str> + ?Sized,
+    B: AsRef<str> + ?Sized,
+{
+    a.as_ref()
+        .chars()
+        .map(fold)
+        .cmp(b.as_ref().chars().map(fold))
+}
+
+fn fold(c: char) -> char {
+    fn single(mut mapped: impl Iterator<Item = char>, c: char) -> char {
+        match (mapped.next(), mapped.next()) {
+            (Some(m), None) => m,
+            _ => c,
+        }
+    }
+    let upper = single(c.to_uppercase(), c);
+    single(upper.to_lowercase(), upper)
+}
+
+/// Split a version into the version proper and the pre-release part, dropping any leading `v`
+/// and build metadata.
+fn split_version(s: &[u8]) -> (&[u8], Option<&[u8]>) {
+    let s = match s.first() {
+        Some(b'v') | Some(b'V') => &s[1..],
+        _ => s,
+    };
+    let s = match s.iter().position(|&c| c == b'+') {
+        Some(i) => &s[..i],
+        None => s,
+    };
+    match s.iter().position(|&c| c == b'-') {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    }
+}
+
+/// Compare components, numbers by value before anything else bytewise.
+fn identifier_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    let number = |s: &[u8]| !s.is_empty() && s.iter().all(u8::is_ascii_digit);
+    match (number(a), number(b)) {
+        (true, true) => numeric_cmp(a, b),
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => a.cmp(b),
+    }
+}
+
+/// Compare strings of ASCII digits by numeric value.
+fn numeric_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    let (a, b) = (trim_zeros(a), trim_zeros(b));
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    &digits[digits.iter().take_while(|&&c| c == b'0').count()..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bisect_left_by, bisect_right_by, insort_right_by};
+    use proptest::prelude::*;
+
+    #[test]
+    fn naturals() {
+        let mut files = vec!["file10", "file2", "file1", "file02", "File3", "file"];
+        files.sort_by(natural);
+        assert_eq!(
+            files,
+            ["File3", "file", "file1", "file2", "file02", "file10"]
+        );
+        assert_eq!(natural("1.9", "1.10"), Ordering::Less);
+        assert_eq!(natural("x99999999999999999999999", "x1"), Ordering::Greater);
+
+        assert_eq!(bisect_left_by(&files, |f| natural(f, "file2")), 3);
+        assert_eq!(bisect_right_by(&files, |f| natural("file2", f)), 5);
+        let mut owned: Vec<String> = files.iter().map(|f| f.to_string()).collect();
+        insort_right_by(&mut owned, "file9".to_string(), natural);
+        assert_eq!(owned[5], "file9");
+    }
+
+    #[test]
+    fn versions() {
+        let sorted = [
+            "0.9",
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.9.3",
+            "v1.10.0",
+        ];
+        for w in sorted.windows(2) {
+            assert_eq!(version(w[0], w[1]), Ordering::Less, "{:?}", w);
+        }
+        assert_eq!(version("1.2", "1.2.0+build.5"), Ordering::Equal);
+        assert_eq!(version("1.02", "v1.2"), Ordering::Equal);
+    }
+
+    #[test]
+    fn dotted() {
+        assert_eq!(dotted_numeric("10.0.0.9", "10.0.0.10"), Ordering::Less);
+        assert_eq!(dotted_numeric("2.3", "2.3.1"), Ordering::Less);
+        assert_eq!(dotted_numeric("1.2", "1.2.0"), Ordering::Less);
+        assert_eq!(dotted_numeric("1.10", "1.x"), Ordering::Less);
+    }
+
+    #[test]
+    fn cases() {
+        assert_eq!(ascii_case_insensitive("apple", "Banana"), Ordering::Less);
+        assert_eq!(ascii_case_insensitive("ABC", "abc"), Ordering::Equal);
+        assert_eq!(
+            ascii_case_insensitive(b"\xc3\x89", b"\xc3\xa9"),
+            Ordering::Less
+        );
+
+        assert_eq!(case_insensitive("ÉCOLE", "école"), Ordering::Equal);
+        assert_eq!(case_insensitive("ΣΊΣΥΦΟΣ", "σίσυφος"), Ordering::Equal);
+        assert_eq!(case_insensitive("Straße", "STRASSE"), Ordering::Greater);
+        assert_eq!(case_insensitive("K", "\u{212A}"), Ordering::Equal);
+    }
+
+    /// Check that `cmp` is a total preorder on `a`, `b` and `c`.
+    fn check_preorder<T: ?Sized>(cmp: impl Fn(&T, &T) -> Ordering, a: &T, b: &T, c: &T) {
+        assert_eq!(cmp(a, a), Ordering::Equal);
+        assert_eq!(cmp(a, b), cmp(b, a).reverse());
+        let mut v = [a, b, c];
+        v.sort_by(|x, y| cmp(x, y));
+        assert_ne!(cmp(v[0], v[2]), Ordering::Greater);
+        assert_ne!(cmp(v[0], v[1]), Ordering::Greater);
+        assert_ne!(cmp(v[1], v[2]), Ordering::Greater);
+        if cmp(v[0], v[1]) == Ordering::Equal && cmp(v[1], v[2]) == Ordering::Equal {
+            assert_eq!(cmp(v[0], v[2]), Ordering::Equal);
+        }
+        if cmp(v[0], v[1]) == Ordering::Less || cmp(v[1], v[2]) == Ordering::Less {
+            assert_eq!(cmp(v[0], v[2]), Ordering::Less);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_total_preorders(
+            a in "[0-9a.+vA-]{0,8}",
+            b in "[0-9a.+vA-]{0,8}",
+            c in "[0-9a.+vA-]{0,8}",
+            d in "[aAßσςΣK\u{212A}é]{0,4}",
+            e in "[aAßσςΣK\u{212A}é]{0,4}",
+            f in "[aAßσςΣK\u{212A}é]{0,4}"
+        ) {
+            check_preorder(natural::<str, str>, &a, &b, &c);
+            check_preorder(version::<str, str>, &a, &b, &c);
+            check_preorder(dotted_numeric::<str, str>, &a, &b, &c);
+            check_preorder(ascii_case_insensitive::<str, str>, &a, &b, &c);
+            check_preorder(case_insensitive::<str, str>, &d, &e, &f);
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! Compressed sorted integer sequences using the Elias–Fano encoding.
+//!
+//! Each value is split into low bits, stored verbatim in a packed array, and high bits, stored
+//! in unary as gaps in a bit vector. A sorted sequence of `n` values below `u` takes about
+//! `2 + log2(u / n)` bits per value, typically a small fraction of a `Vec<u64>` for posting
+//! lists and id sets, and can be searched and decoded without decompressing it.
+
+use std::iter::FusedIterator;
+use std::mem;
+
+/// Number of ones (and zeros) of the high bits between consecutive select samples.
+const SAMPLE: usize = 256;
+
+/// Sorted sequence of unsigned integers in Elias–Fano encoding.
+///
+/// Queries behave exactly as the slice functions do on the sequence the encoding was built
+/// from, including with duplicate values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EliasFano {
+    len: usize,
+    /// Number of low bits stored verbatim for each value.
+    low_bits: u32,
+    /// Packed low bits of each value.
+    low: Vec<u64>,
+    /// Value `i` with high bits `h` sets bit `h + i`.
+    high: Vec<u64>,
+    /// Largest high bits of any value.
+    max_high: u64,
+    /// Positions in `high` of every `SAMPLE`th one.
+    ones: Vec<usize>,
+    /// Positions in `high` of every `SAMPLE`th zero.
+    zeros: Vec<usize>,
+}
+
+impl EliasFano {
+    /// Encode `sorted`, which may hold `u8`, `u16`, `u32` or `u64` values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sorted` is not sorted.
+    pub fn new<T>(sorted: &[T]) -> Self
+    where
+        T: Copy + Into<u64>,
+    {
+        let len = sorted.len();
+        let max = sorted.last().map_or(0, |&v| v.into());
+        let universe = max as u128 + 1;
+        let low_bits = if universe > len as u128 && len > 0 {
+            (universe / len as u128).ilog2().min(63)
+        } else {
+            0
+        };
+        let max_high = max >> low_bits;
+        let high_len = len + max_high as usize + 1;
+
+        let mut low = vec![0; (len * low_bits as usize).div_ceil(64)];
+        let mut high = vec![0; high_len.div_ceil(64)];
+        let mut previous = 0;
+        for (i, &v) in sorted.iter().enumerate() {
+            let v: u64 = v.into();
+            assert!(previous <= v, "values are not sorted");
+            previous = v;
+            if low_bits > 0 {
+                let pos = i * low_bits as usize;
+                let bits = v & low_mask(low_bits);
+                low[pos / 64] |= bits << (pos % 64);
+                if pos % 64 + low_bits as usize > 64 {
+                    low[pos / 64 + 1] |= bits >> (64 - pos % 64);
+                }
+            }
+            let pos = (v >> low_bits) as usize + i;
+            high[pos / 64] |= 1 << (pos % 64);
+        }
+
+        let (mut ones, mut zeros) = (vec![], vec![]);
+        let (mut one, mut zero) = (0, 0);
+        for pos in 0..high_len {
+            if high[pos / 64] >> (pos % 64) & 1 == 1 {
+                if one % SAMPLE == 0 {
+                    ones.push(pos);
+                }
+                one += 1;
+            } else {
+                if zero % SAMPLE == 0 {
+                    zeros.push(pos);
+                }
+                zero += 1;
+            }
+        }
+
+        EliasFano {
+            len,
+            low_bits,
+            low,
+            high,
+            max_high,
+            ones,
+            zeros,
+        }
+    }
+
+    /// Return the number of values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return `true` if there are no values.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return the size of the encoding in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        mem::size_of::<Self>()
+            + (self.low.len() + self.high.len()) * mem::size_of::<u64>()
+            + (self.ones.len() + self.zeros.len()) * mem::size_of::<usize>()
+    }
+
+    /// Return the value at `index`, or `None` if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> Option<u64> {
+        if index >= self.len {
+            return None;
+        }
+        let high = (self.select(index, true) - index) as u64;
+        Some(high << self.low_bits | self.low(index))
+    }
+
+    /// Return the index where `x` should be inserted, assuming the values are sorted.
+    ///
+    /// Equivalent to [`bisect_left`](crate::bisect_left) on the sequence the encoding was built
+    /// from.
+    pub fn bisect_left(&self, x: u64) -> usize {
+        let h = x >> self.low_bits;
+        if self.is_empty() || h > self.max_high {
+            return self.len;
+        }
+        // Values with high bits `h` lie between the `h - 1`th and `h`th zeros.
+        let h = h as usize;
+        let start = match h {
+            0 => 0,
+            _ => self.select(h - 1, false) - (h - 1),
+        };
+        let end = self.select(h, false) - h;
+        let x = x & low_mask(self.low_bits);
+        partition(start, end, |i| self.low(i) < x)
+    }
+
+    /// Return the index where `x` should be inserted, assuming the values are sorted.
+    ///
+    /// Equivalent to [`bisect_right`](crate::bisect_right) on the sequence the encoding was
+    /// built from.
+    pub fn bisect_right(&self, x: u64) -> usize {
+        match x.checked_add(1) {
+            Some(next) => self.bisect_left(next),
+            None => self.len,
+        }
+    }
+
+    /// Return the smallest value greater than or equal to `x`, if any.
+    pub fn next_geq(&self, x: u64) -> Option<u64> {
+        self.get(self.bisect_left(x))
+    }
+
+    /// Return an iterator over the values, decoding them one at a time.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            ef: self,
+            index: 0,
+            pos: 0,
+        }
+    }
+
+    fn low(&self, index: usize) -> u64 {
+        if self.low_bits == 0 {
+            return 0;
+        }
+        let pos = index * self.low_bits as usize;
+        let (word, offset) = (pos / 64, pos % 64);
+        let mut bits = self.low[word] >> offset;
+        if offset + self.low_bits as usize > 64 {
+            bits |= self.low[word + 1] << (64 - offset);
+        }
+        bits & low_mask(self.low_bits)
+    }
+
+    /// Return the position in `high` of the `k`th one, or of the `k`th zero if `ones` is `false`.
+    fn select(&self, k: usize, ones: bool) -> usize {
+        let samples = if ones { &self.ones } else { &self.zeros };
+        let pos = samples[k / SAMPLE];
+        let mut rank = k % SAMPLE;
+        let mut word = pos / 64;
+        let read = |w: usize| if ones { self.high[w] } else { !self.high[w] };
+        let mut bits = read(word) & (!0 << (pos % 64));
+        loop {
+            let count = bits.count_ones() as usize;
+            if rank < count {
+                for _ in 0..rank {
+                    bits &= bits - 1;
+                }
+                return word * 64 + bits.trailing_zeros() as usize;
+            }
+            rank -= count;
+            word += 1;
+            bits = read(word);
+        }
+    }
+}
+
+fn low_mask(bits: u32) -> u64 {
+    (1 << bits) - 1
+}
+
+/// Return the first index in `lo..hi` for which `before` is `false`.
+fn partition<P>(mut lo: usize, mut hi: usize, mut before: P) -> usize
+where
+    P: FnMut(usize) -> bool,
+{
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if before(mid) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+impl<'a> IntoIterator for &'a EliasFano {
+    type Item = u64;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// Iterator over the values of an [`EliasFano`] sequence.
+#[derive(Clone, Debug)]
+pub struct Iter<'a> {
+    ef: &'a EliasFano,
+    index: usize,
+    /// Position in the high bits from which to look for the next one.
+    pos: usize,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.index == self.ef.len {
+            return None;
+        }
+        let mut word = self.pos / 64;
+        let mut bits = self.ef.high[word] & (!0 << (self.pos % 64));
+        while bits == 0 {
+            word += 1;
+            bits = self.ef.high[word];
+        }
+        let pos = word * 64 + bits.trailing_zeros() as usize;
+        let high = (pos - self.index) as u64;
+        let value = high << self.ef.low_bits | self.ef.low(self.index);
+        self.index += 1;
+        self.pos = pos + 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.ef.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl FusedIterator for Iter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bisect_left, bisect_right};
+    use proptest::prelude::*;
+
+    #[test]
+    fn extremes() {
+        let values = [0_u64, 0, 7, 1 << 40, u64::MAX - 1, u64::MAX, u64::MAX];
+        let ef = EliasFano::new(&values);
+        assert!(ef.iter().eq(values.iter().copied()));
+        assert_eq!(ef.get(3), Some(1 << 40));
+        assert_eq!(ef.get(7), None);
+        assert_eq!(ef.bisect_left(u64::MAX), 5);
+        assert_eq!(ef.bisect_right(u64::MAX), 7);
+        assert_eq!(ef.bisect_right(0), 2);
+        assert_eq!(ef.next_geq(8), Some(1 << 40));
+
+        let empty = EliasFano::new::<u32>(&[]);
+        assert_eq!(empty.bisect_left(5), 0);
+        assert_eq!(empty.next_geq(0), None);
+        assert_eq!(empty.iter().next(), None);
+    }
+
+    #[test]
+    fn compresses() {
+        let ids: Vec<u32> = (0..100_000).map(|i| i * 37).collect();
+        let ef = EliasFano::new(&ids);
+        assert!(ef.size_in_bytes() < ids.len() * mem::size_of::<u32>() / 3);
+        assert_eq!(ef.get(99_999), Some(99_999 * 37));
+        assert_eq!(ef.bisect_left(37 * 5000 + 1), 5001);
+    }
+
+    #[test]
+    #[should_panic]
+    fn unsorted() {
+        EliasFano::new(&[3_u64, 1]);
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_slice(
+            mut nums in prop::collection::vec(any::<u64>(), 0..300),
+            small in prop::collection::vec(0..2000_u64, 0..2000),
+            x in any::<u64>(),
+            y in 0..2100_u64
+        ) {
+            nums.sort();
+            let ef = EliasFano::new(&nums);
+            prop_assert_eq!(ef.bisect_left(x), bisect_left(&nums, &x));
+            prop_assert_eq!(ef.bisect_right(x), bisect_right(&nums, &x));
+            prop_assert!(ef.iter().eq(nums.iter().copied()));
+
+            let mut small = small;
+            small.sort();
+            let ef = EliasFano::new(&small);
+            prop_assert_eq!(ef.bisect_left(y), bisect_left(&small, &y));
+            prop_assert_eq!(ef.bisect_right(y), bisect_right(&small, &y));
+            prop_assert_eq!(ef.next_geq(y), small.get(bisect_left(&small, &y)).copied());
+            for (i, &v) in small.iter().enumerate() {
+                prop_assert_eq!(ef.get(i), Some(v));
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Static search index over sorted data using the Eytzinger (BFS) layout.
+//!
+//! Elements are stored in the order of a breadth first traversal of the implicit binary search
+//! tree over the sorted data. The top levels of the tree share a handful of cache lines and the
+//! next levels of the search can be prefetched ahead of time, which makes lookups into large
+//! read-only tables considerably faster than a plain binary search.
+
+use std::cmp::Ordering;
+
+/// Read-only index over a sorted slice answering `bisect_left`/`bisect_right` queries.
+///
+/// Indices returned by queries refer to positions in the *original* sorted slice, so an
+/// `EytzingerIndex` can replace calls such as `bisect_left(&table, &x)` without any other
+/// changes.
+#[derive(Clone, Debug)]
+pub struct EytzingerIndex<T> {
+    /// Elements in Eytzinger order.
+    data: Vec<T>,
+    /// `ranks[k]` is the index in the sorted slice of `data[k]`.
+    ranks: Vec<usize>,
+}
+
+impl<T> EytzingerIndex<T> {
+    /// Build an index from `sorted`, assuming it is sorted.
+    pub fn new(sorted: &[T]) -> Self
+    where
+        T: Clone,
+    {
+        let mut ranks = vec![0; sorted.len()];
+        let mut next = 0;
+        fill_ranks(&mut ranks, &mut next, 0);
+        let data = ranks.iter().map(|&r| sorted[r].clone()).collect();
+        EytzingerIndex { data, ranks }
+    }
+
+    /// Return the number of elements in the index.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Return `true` if the index contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Return the index where `x` should be inserted in the original sorted slice.
+    ///
+    /// Equivalent to [`bisect_left`](crate::bisect_left) on the slice the index was built from.
+    pub fn bisect_left(&self, x: &T) -> usize
+    where
+        T: Ord,
+    {
+        self.bisect_left_by(|p| p.cmp(x))
+    }
+
+    /// Return the index where `x` should be inserted in the original sorted slice.
+    ///
+    /// Equivalent to [`bisect_right`](crate::bisect_right) on the slice the index was built
+    /// from.
+    pub fn bisect_right(&self, x: &T) -> usize
+    where
+        T: Ord,
+    {
+        self.bisect_right_by(|p| x.cmp(p))
+    }
+
+    /// Return the index where a value should be inserted in the original sorted slice,
+    /// according to a comparator function.
+    ///
+    /// The comparator follows the same contract as [`bisect_left_by`](crate::bisect_left_by).
+    pub fn bisect_left_by<F>(&self, mut f: F) -> usize
+    where
+        F: FnMut(&T) -> Ordering,
+    {
+        self.partition_point(|p| f(p) == Ordering::Less)
+    }
+
+    /// Return the index where a value should be inserted in the original sorted slice,
+    /// according to a comparator function.
+    ///
+    /// The comparator follows the same contract as [`bisect_right_by`](crate::bisect_right_by).
+    pub fn bisect_right_by<F>(&self, mut f: F) -> usize
+    where
+        F: FnMut(&T) -> Ordering,
+    {
+        self.partition_point(|p| f(p) != Ordering::Less)
+    }
+
+    /// Return the sorted index of the first element for which `go_right` is `false`.
+    fn partition_point<F>(&self, mut go_right: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let n = self.data.len();
+        let mut k = 0;
+        while k < n {
+            // Four levels down from `k` the descendants are 16 consecutive elements.
+            prefetch(self.data.as_ptr().wrapping_add(16 * (k + 1) - 1));
+            k = 2 * k + 1 + go_right(&self.data[k]) as usize;
+        }
+        // Undo the right turns taken after the last left turn; that node is the answer.
+        let mut j = k + 1;
+        j >>= j.trailing_ones() + 1;
+        if j == 0 {
+            n
+        } else {
+            self.ranks[j - 1]
+        }
+    }
+}
+
+/// Assign sorted indices to the Eytzinger positions rooted at `k` by an in-order traversal.
+fn fill_ranks(ranks: &mut [usize], next: &mut usize, k: usize) {
+    if k < ranks.len() {
+        fill_ranks(ranks, next, 2 * k + 1);
+        ranks[k] = *next;
+        *next += 1;
+        fill_ranks(ranks, next, 2 * k + 2);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+fn prefetch<T>(p: *const T) {
+    use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+    // SAFETY: prefetching is only a hint and never dereferences `p`, so it may point anywhere.
+    unsafe { _mm_prefetch::<_MM_HINT_T0>(p as *const i8) }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+#[inline(always)]
+fn prefetch<T>(_p: *const T) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bisect_left, bisect_right};
+    use proptest::prelude::*;
+
+    #[test]
+    fn empty() {
+        let index = EytzingerIndex::<u32>::new(&[]);
+        assert!(index.is_empty());
+        assert_eq!(index.bisect_left(&1), 0);
+        assert_eq!(index.bisect_right(&1), 0);
+    }
+
+    #[test]
+    fn duplicates() {
+        let a = [1, 2, 2, 3, 3, 3, 4, 4, 4, 4];
+        let index = EytzingerIndex::new(&a);
+        for x in 0..6 {
+            assert_eq!(index.bisect_left(&x), bisect_left(&a, &x));
+            assert_eq!(index.bisect_right(&x), bisect_right(&a, &x));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_bisect(
+            mut nums in prop::collection::vec(0..1000_u32, 0..500),
+            num in 0..1000_u32
+        ) {
+            nums.sort();
+            let index = EytzingerIndex::new(&nums);
+
+            prop_assert_eq!(index.len(), nums.len());
+            prop_assert_eq!(index.bisect_left(&num), bisect_left(&nums, &num));
+            prop_assert_eq!(index.bisect_right(&num), bisect_right(&nums, &num));
+        }
+    }
+}
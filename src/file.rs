@@ -0,0 +1,395 @@
+//! Bisection over the lines of sorted text files, without loading them into memory.
+//!
+//! Like `look(1)`, the search seeks to the middle of a byte range, skips forward to the start of
+//! the next line and compares that line, so finding a line in a file of `n` bytes reads
+//! `O(log n)` lines. Positions are byte offsets of line starts, so that
+//! `equal_range_by(..)` gives the bytes holding every matching line.
+//!
+//! Lines end in `\n`, with an optional `\r` before it which is not passed to comparators. The
+//! last line need not end in a newline, and lines can be arbitrarily long.
+
+use std::cmp::Ordering;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::{Bound::*, Range, RangeBounds};
+
+const CHUNK: usize = 8 * 1024;
+
+/// Return the offset of the first line of `reader` which is not less than `key`, comparing
+/// lines bytewise, assuming the lines are sorted.
+pub fn bisect_left<R>(reader: &mut R, key: &[u8]) -> io::Result<u64>
+where
+    R: Read + Seek + ?Sized,
+{
+    bisect_left_by(reader, |line| line.cmp(key))
+}
+
+/// Return the offset of the first line of `reader` which is greater than `key`, comparing lines
+/// bytewise, assuming the lines are sorted.
+pub fn bisect_right<R>(reader: &mut R, key: &[u8]) -> io::Result<u64>
+where
+    R: Read + Seek + ?Sized,
+{
+    bisect_right_by(reader, |line| key.cmp(line))
+}
+
+/// Return the offset of the first line of `reader` for which `f` does not return `Less`,
+/// assuming the lines are sorted.
+///
+/// The comparator function follows the same contract as
+/// [`bisect_left_by`](crate::bisect_left_by), and is called with lines stripped of their line
+/// ending.
+pub fn bisect_left_by<R, F>(reader: &mut R, f: F) -> io::Result<u64>
+where
+    R: Read + Seek + ?Sized,
+    F: FnMut(&[u8]) -> Ordering,
+{
+    bisect_left_slice_by(reader, .., f)
+}
+
+/// Return the offset of the first line of `reader` for which `f` returns `Less`, assuming the
+/// lines are sorted.
+///
+/// The comparator function follows the same contract as
+/// [`bisect_right_by`](crate::bisect_right_by), and is called with lines stripped of their line
+/// ending.
+pub fn bisect_right_by<R, F>(reader: &mut R, f: F) -> io::Result<u64>
+where
+    R: Read + Seek + ?Sized,
+    F: FnMut(&[u8]) -> Ordering,
+{
+    bisect_right_slice_by(reader, .., f)
+}
+
+/// Return the offset of the first line in the bytes `within` of `reader` for which `f` does not
+/// return `Less`, assuming the lines are sorted.
+///
+/// `within` must start at the start of a line.
+///
+/// # Errors
+///
+/// Returns an error of kind `InvalidInput` if `within` is out of bounds of `reader`, and any
+/// error from reading or seeking `reader`.
+pub fn bisect_left_slice_by<R, I, F>(reader: &mut R, within: I, mut f: F) -> io::Result<u64>
+where
+    R: Read + Seek + ?Sized,
+    I: RangeBounds<u64>,
+    F: FnMut(&[u8]) -> Ordering,
+{
+    let mut lines = LineReader::new(reader);
+    let within = lines.resolve(within)?;
+    lines.partition_point(within, |line| f(line) == Ordering::Less)
+}
+
+/// Return the offset of the first line in the bytes `within` of `reader` for which `f` returns
+/// `Less`, assuming the lines are sorted.
+///
+/// `within` must start at the start of a line.
+///
+/// # Errors
+///
+/// Returns an error of kind `InvalidInput` if `within` is out of bounds of `reader`, and any
+/// error from reading or seeking `reader`.
+pub fn bisect_right_slice_by<R, I, F>(reader: &mut R, within: I, mut f: F) -> io::Result<u64>
+where
+    R: Read + Seek + ?Sized,
+    I: RangeBounds<u64>,
+    F: FnMut(&[u8]) -> Ordering,
+{
+    let mut lines = LineReader::new(reader);
+    let within = lines.resolve(within)?;
+    lines.partition_point(within, |line| f(line) != Ordering::Less)
+}
+
+/// Return the bytes of `reader` holding the lines for which `f` returns `Equal`, assuming the
+/// lines are sorted.
+///
+/// Unlike [`bisect_right_by`], `f` compares its argument *to* the target, as for
+/// [`bisect_left_by`], so the same comparator finds both ends of the range.
+pub fn equal_range_by<R, F>(reader: &mut R, mut f: F) -> io::Result<Range<u64>>
+where
+    R: Read + Seek + ?Sized,
+    F: FnMut(&[u8]) -> Ordering,
+{
+    let mut lines = LineReader::new(reader);
+    let within = lines.resolve(..)?;
+    let start = lines.partition_point(within.clone(), |line| f(line) == Ordering::Less)?;
+    let end = lines.partition_point(start..within.end, |line| f(line) != Ordering::Greater)?;
+    Ok(start..end)
+}
+
+/// Return a comparator matching lines which start with `key`, for use with [`equal_range_by`].
+pub fn prefix(key: &[u8]) -> impl Fn(&[u8]) -> Ordering + '_ {
+    move |line| line[..line.len().min(key.len())].cmp(key)
+}
+
+/// Return a comparator matching lines equal to `key` ignoring ASCII case, for use with
+/// [`equal_range_by`].
+///
+/// The lines must be sorted ignoring ASCII case, as by `sort -f`.
+pub fn ignore_ascii_case(key: &[u8]) -> impl Fn(&[u8]) -> Ordering + '_ {
+    move |line| {
+        let fold = |b: &u8| b.to_ascii_lowercase();
+        line.iter().map(fold).cmp(key.iter().map(fold))
+    }
+}
+
+/// Wrap the comparator `f` to compare field `index`, counting from zero, of lines split on
+/// `separator`.
+///
+/// Lines with fewer fields are compared as if the field were empty.
+pub fn field<F>(separator: u8, index: usize, mut f: F) -> impl FnMut(&[u8]) -> Ordering
+where
+    F: FnMut(&[u8]) -> Ordering,
+{
+    move |line| f(line.split(|&b| b == separator).nth(index).unwrap_or(&[]))
+}
+
+/// Reads individual lines at arbitrary offsets of a seekable source.
+pub(crate) struct LineReader<'r, R: ?Sized> {
+    reader: &'r mut R,
+    line: Vec<u8>,
+    chunk: Vec<u8>,
+}
+
+impl<'r, R: Read + Seek + ?Sized> LineReader<'r, R> {
+    pub(crate) fn new(reader: &'r mut R) -> Self {
+        LineReader {
+            reader,
+            line: Vec::new(),
+            chunk: vec![0; CHUNK],
+        }
+    }
+
+    /// Convert bounds to a byte range, checking it is in bounds of the source.
+    pub(crate) fn resolve<I: RangeBounds<u64>>(&mut self, within: I) -> io::Result<Range<u64>> {
+        let len = self.reader.seek(SeekFrom::End(0))?;
+        let lo = match within.start_bound() {
+            Unbounded => Some(0),
+            Included(i) => Some(*i),
+            Excluded(i) => i.checked_add(1),
+        };
+        let hi = match within.end_bound() {
+            Unbounded => Some(len),
+            Included(i) => i.checked_add(1),
+            Excluded(i) => Some(*i),
+        };
+        match (lo, hi) {
+            (Some(lo), Some(hi)) if lo <= hi && hi <= len => Ok(lo..hi),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "byte range out of bounds",
+            )),
+        }
+    }
+
+    /// Return the offset of the first line in `within` for which `before` is `false`, assuming
+    /// it is `true` for all lines before that one and `false` for all lines after.
+    pub(crate) fn partition_point<P>(
+        &mut self,
+        within: Range<u64>,
+        mut before: P,
+    ) -> io::Result<u64>
+    where
+        P: FnMut(&[u8]) -> bool,
+    {
+        // Invariant: `lo` is the start of a line, all lines starting before `lo` are before the
+        // boundary and all lines starting at or after `hi` are not.
+        let (mut lo, mut hi) = (within.start, within.end);
+        // Lines are read in full even where `hi` is in the middle of one, so `lo` always ends
+        // up at the start of a line, possibly after `hi`.
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let start = if mid == lo {
+                lo
+            } else {
+                self.line_start_from(mid, hi)?
+            };
+            if start >= hi {
+                // No line starts in `mid..hi`.
+                hi = mid;
+                continue;
+            }
+            let next = self.read_line(start, within.end)?;
+            if before(self.line()) {
+                lo = next;
+            } else {
+                hi = start;
+            }
+        }
+        Ok(lo)
+    }
+
+    /// Return the offset of the first line starting at or after `pos`, or `end` if there is
+    /// none before `end`.
+    pub(crate) fn line_start_from(&mut self, pos: u64, end: u64) -> io::Result<u64> {
+        if pos == 0 {
+            return Ok(0);
+        }
+        // `pos` starts a line if the byte before it ends one.
+        let mut offset = pos - 1;
+        self.reader.seek(SeekFrom::Start(offset))?;
+        while offset < end {
+            let n = self.read_chunk(end - offset)?;
+            if n == 0 {
+                break;
+            }
+            if let Some(i) = self.chunk[..n].iter().position(|&b| b == b'\n') {
+                return Ok((offset + i as u64 + 1).min(end));
+            }
+            offset += n as u64;
+        }
+        Ok(end)
+    }
+
+    /// Read the line starting at `start`, stopping at `end`, and return the offset of the start
+    /// of the next line.
+    ///
+    /// The line is then available from [`line`](LineReader::line).
+    pub(crate) fn read_line(&mut self, start: u64, end: u64) -> io::Result<u64> {
+        self.line.clear();
+        self.reader.seek(SeekFrom::Start(start))?;
+        let mut offset = start;
+        while offset < end {
+            let n = self.read_chunk(end - offset)?;
+            if n == 0 {
+                break;
+            }
+            if let Some(i) = self.chunk[..n].iter().position(|&b| b == b'\n') {
+                self.line.extend_from_slice(&self.chunk[..i]);
+                return Ok(offset + i as u64 + 1);
+            }
+            self.line.extend_from_slice(&self.chunk[..n]);
+            offset += n as u64;
+        }
+        Ok(offset)
+    }
+
+    /// Return the last line read, without its line ending.
+    pub(crate) fn line(&self) -> &[u8] {
+        match self.line.last() {
+            Some(b'\r') => &self.line[..self.line.len() - 1],
+            _ => &self.line,
+        }
+    }
+
+    /// Read up to `limit` bytes into the chunk buffer.
+    fn read_chunk(&mut self, limit: u64) -> io::Result<usize> {
+        let len = limit.min(self.chunk.len() as u64) as usize;
+        loop {
+            match self.reader.read(&mut self.chunk[..len]) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::io::Cursor;
+
+    const WORDS: &[u8] = b"apple\napricot\nbanana\nbanana\ncherry\ndate";
+
+    #[test]
+    fn words() {
+        let mut r = Cursor::new(WORDS);
+        assert_eq!(bisect_left(&mut r, b"banana").unwrap(), 14);
+        assert_eq!(bisect_right(&mut r, b"banana").unwrap(), 28);
+        assert_eq!(bisect_left(&mut r, b"a").unwrap(), 0);
+        assert_eq!(bisect_left(&mut r, b"date").unwrap(), 35);
+        assert_eq!(bisect_right(&mut r, b"date").unwrap(), 39);
+        assert_eq!(bisect_left(&mut Cursor::new(b""), b"x").unwrap(), 0);
+
+        assert_eq!(equal_range_by(&mut r, prefix(b"ap")).unwrap(), 0..14);
+        assert_eq!(equal_range_by(&mut r, prefix(b"b")).unwrap(), 14..28);
+        assert_eq!(equal_range_by(&mut r, prefix(b"c")).unwrap(), 28..35);
+        assert_eq!(equal_range_by(&mut r, prefix(b"e")).unwrap(), 39..39);
+
+        assert_eq!(
+            bisect_left_slice_by(&mut r, 14.., |line| line.cmp(&b"apricot"[..])).unwrap(),
+            14
+        );
+        assert_eq!(
+            bisect_left_slice_by(&mut r, ..100, |line| line.cmp(&b"a"[..]))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            bisect_right_slice_by(&mut r, ..=u64::MAX, |line| b"a"[..].cmp(line))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            bisect_left_slice_by(&mut r, (Excluded(u64::MAX), Unbounded), |line| {
+                line.cmp(&b"a"[..])
+            })
+            .unwrap_err()
+            .kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn crlf_and_fields() {
+        let data = b"1,amy\r\n2,Bob\r\n3,zed\r\n";
+        let mut r = Cursor::new(&data[..]);
+        let key = field(b',', 1, ignore_ascii_case(b"bob"));
+        assert_eq!(equal_range_by(&mut r, key).unwrap(), 7..14);
+        let key = field(b',', 0, |f: &[u8]| f.cmp(b"2"));
+        assert_eq!(equal_range_by(&mut r, key).unwrap(), 7..14);
+        assert_eq!(bisect_right(&mut r, b"3,zed").unwrap(), 21);
+    }
+
+    #[test]
+    fn long_lines() {
+        let mut data = vec![];
+        for c in b"abc" {
+            data.extend(std::iter::repeat_n(*c, 3 * CHUNK));
+            data.push(b'\n');
+        }
+        let mut r = Cursor::new(&data);
+        let line = 3 * CHUNK as u64 + 1;
+        assert_eq!(
+            equal_range_by(&mut r, prefix(b"b")).unwrap(),
+            line..2 * line
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_slice(
+            mut lines in prop::collection::vec("[a-c]{0,4}", 0..100),
+            key in "[a-c]{0,4}",
+            crlf in any::<bool>(),
+            trailing in any::<bool>()
+        ) {
+            lines.sort();
+            let ending = if crlf { "\r\n" } else { "\n" };
+            let mut text = lines.join(ending);
+            if trailing && !lines.is_empty() {
+                text.push_str(ending);
+            }
+            // Offset of the start of each line, and of the end of the data.
+            let mut offsets: Vec<u64> = lines
+                .iter()
+                .scan(0, |pos, line| {
+                    let start = *pos;
+                    *pos += (line.len() + ending.len()) as u64;
+                    Some(start)
+                })
+                .collect();
+            offsets.push(text.len() as u64);
+
+            let mut r = Cursor::new(text.as_bytes());
+            let key = key.as_bytes();
+            let left = crate::bisect_left_by(&lines, |l| l.as_bytes().cmp(key));
+            let right = crate::bisect_right_by(&lines, |l| key.cmp(l.as_bytes()));
+            prop_assert_eq!(bisect_left(&mut r, key).unwrap(), offsets[left]);
+            prop_assert_eq!(bisect_right(&mut r, key).unwrap(), offsets[right]);
+        }
+    }
+}
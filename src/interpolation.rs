@@ -0,0 +1,162 @@
+//! Interpolation search over sorted numeric slices.
+//!
+//! Rather than probing the midpoint of the remaining range, each probe is placed where `x` would
+//! be if the values in the range were evenly spread between its endpoints. For roughly uniformly
+//! distributed keys such as ids or timestamps this needs `O(log log n)` probes. Skewed data can
+//! make the guesses arbitrarily poor, so after a bounded number of probes that fail to shrink the
+//! range appreciably the search falls back to ordinary bisection.
+
+use crate::bisect_left_slice_by;
+use std::cmp::Ordering;
+
+/// Number of poorly placed probes tolerated before falling back to bisection.
+const MAX_BAD_PROBES: usize = 4;
+
+/// Numeric types whose values can be interpolated between.
+pub trait Numeric: Copy + PartialOrd {
+    /// Convert to an `f64` for estimating positions. Precision loss only affects probe placement.
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! numeric {
+    ($($t:ty),*) => {
+        $(
+            impl Numeric for $t {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+            }
+        )*
+    };
+}
+
+numeric!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+/// Return the index where `x` should be inserted in `a`, assuming `a` is sorted.
+///
+/// The return value is identical to that of [`bisect_left`](crate::bisect_left): all `e` in
+/// `a[..i]` have `e < x`, and all `e` in `a[i..]` have `e >= x`.
+///
+/// The result is unspecified if `a` or `x` contain values that are not comparable, such as `NaN`.
+pub fn interpolation_bisect_left<T: Numeric>(a: &[T], x: &T) -> usize {
+    let x = *x;
+    interpolation_partition(a, x, |e| e < x)
+}
+
+/// Return the index where `x` should be inserted in `a`, assuming `a` is sorted.
+///
+/// The return value is identical to that of [`bisect_right`](crate::bisect_right): all `e` in
+/// `a[..i]` have `e <= x`, and all `e` in `a[i..]` have `e > x`.
+///
+/// The result is unspecified if `a` or `x` contain values that are not comparable, such as `NaN`.
+pub fn interpolation_bisect_right<T: Numeric>(a: &[T], x: &T) -> usize {
+    let x = *x;
+    interpolation_partition(a, x, |e| e <= x)
+}
+
+/// Return the index of the first element of `a` for which `before` is `false`, placing probes by
+/// interpolating `x` between the endpoints of the remaining range.
+fn interpolation_partition<T, F>(a: &[T], x: T, before: F) -> usize
+where
+    T: Numeric,
+    F: Fn(T) -> bool,
+{
+    // Invariant: `before` holds for all of `a[..lo]` and for none of `a[hi..]`.
+    let (mut lo, mut hi) = (0, a.len());
+    let mut bad_probes = 0;
+
+    while lo < hi {
+        let (first, last) = (a[lo], a[hi - 1]);
+        if !before(first) {
+            return lo;
+        }
+        if before(last) {
+            return hi;
+        }
+        if bad_probes == MAX_BAD_PROBES {
+            return bisect_left_slice_by(a, lo..hi, |&e| {
+                if before(e) {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            });
+        }
+
+        // The boundary lies strictly after `lo` and no later than `hi - 1`.
+        let fraction = (x.to_f64() - first.to_f64()) / (last.to_f64() - first.to_f64());
+        let guess = lo + (fraction * (hi - 1 - lo) as f64) as usize;
+        let probe = guess.max(lo + 1).min(hi - 1);
+
+        let width = hi - lo;
+        if before(a[probe]) {
+            lo = probe + 1;
+        } else {
+            hi = probe;
+        }
+        if (hi - lo) * 4 > width * 3 {
+            bad_probes += 1;
+        }
+    }
+    lo
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bisect_left, bisect_right};
+    use proptest::prelude::*;
+
+    #[test]
+    fn empty() {
+        assert_eq!(interpolation_bisect_left::<u32>(&[], &1), 0);
+        assert_eq!(interpolation_bisect_right::<u32>(&[], &1), 0);
+    }
+
+    #[test]
+    fn duplicates() {
+        let a = [1, 2, 2, 3, 3, 3, 4, 4, 4, 4];
+        for x in 0..6 {
+            assert_eq!(interpolation_bisect_left(&a, &x), bisect_left(&a, &x));
+            assert_eq!(interpolation_bisect_right(&a, &x), bisect_right(&a, &x));
+        }
+    }
+
+    #[test]
+    fn skewed() {
+        // Exponentially growing keys defeat interpolation and force the bisection fallback.
+        let a: Vec<u64> = (0..64).map(|i| 1 << i).collect();
+        for &x in &[0, 1, 3, 1 << 10, (1 << 40) + 1, u64::MAX] {
+            assert_eq!(interpolation_bisect_left(&a, &x), bisect_left(&a, &x));
+            assert_eq!(interpolation_bisect_right(&a, &x), bisect_right(&a, &x));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_integers_match_bisect(
+            mut nums in prop::collection::vec(any::<i64>(), 0..500),
+            num in any::<i64>()
+        ) {
+            nums.sort();
+            prop_assert_eq!(interpolation_bisect_left(&nums, &num), bisect_left(&nums, &num));
+            prop_assert_eq!(interpolation_bisect_right(&nums, &num), bisect_right(&nums, &num));
+        }
+
+        #[test]
+        fn test_floats_match_partition_point(
+            mut nums in prop::collection::vec(-1e6..1e6_f64, 0..500),
+            num in -1e6..1e6_f64
+        ) {
+            nums.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            prop_assert_eq!(
+                interpolation_bisect_left(&nums, &num),
+                nums.partition_point(|&e| e < num)
+            );
+            prop_assert_eq!(
+                interpolation_bisect_right(&nums, &num),
+                nums.partition_point(|&e| e <= num)
+            );
+        }
+    }
+}
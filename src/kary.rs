@@ -0,0 +1,126 @@
+//! Bisection with several concurrent probes per round.
+//!
+//! When evaluating the predicate is slow but `k` evaluations can run at once, probing `k - 1`
+//! evenly spaced indices per round splits the range into `k` parts instead of two, cutting the
+//! number of rounds from `log2(n)` to `logk(n)`.
+
+use std::ops::Range;
+
+/// Result of a k-ary search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KaryOutcome {
+    /// Index of the first element at or after the boundary.
+    pub index: usize,
+    /// Number of times the batch predicate was called.
+    pub rounds: usize,
+}
+
+/// Return the index of the first element of `within` which is not before the boundary,
+/// evaluating up to `k - 1` probes per round.
+///
+/// `batch` is called with the indices to probe in increasing order and must return, for each of
+/// them, `true` if that index is before the boundary, as with `slice::partition_point`. The
+/// indices can be evaluated concurrently.
+///
+/// To find the same index as [`bisect_left_by`](crate::bisect_left_by), return
+/// `f(&a[i]) == Less` for each index `i`; for [`bisect_right_by`](crate::bisect_right_by),
+/// return `f(&a[i]) != Less`.
+///
+/// # Panics
+///
+/// Panics if `k` is less than 2, or if `batch` does not return one result per index.
+pub fn bisect_kary<F>(within: Range<usize>, k: usize, mut batch: F) -> KaryOutcome
+where
+    F: FnMut(&[usize]) -> Vec<bool>,
+{
+    assert!(k >= 2, "k-ary search needs k >= 2");
+    let (mut lo, mut hi) = (within.start, within.end);
+    let mut rounds = 0;
+    let mut probes = Vec::with_capacity(k - 1);
+
+    while lo < hi {
+        let width = hi - lo;
+        probes.clear();
+        if width < k {
+            probes.extend(lo..hi);
+        } else {
+            probes.extend((1..k).map(|j| lo + j * width / k));
+        }
+
+        let before = batch(&probes);
+        assert_eq!(before.len(), probes.len(), "one result per probe expected");
+        rounds += 1;
+
+        // Results for a consistent predicate are all `true` up to the boundary, then `false`.
+        let j = before.iter().take_while(|&&b| b).count();
+        if j > 0 {
+            lo = probes[j - 1] + 1;
+        }
+        if j < probes.len() {
+            hi = probes[j];
+        }
+    }
+
+    KaryOutcome { index: lo, rounds }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bisect_left, bisect_right};
+    use proptest::prelude::*;
+
+    #[test]
+    fn rounds_used() {
+        let outcome = bisect_kary(0..1000, 10, |probes| {
+            probes.iter().map(|&i| i < 437).collect()
+        });
+        assert_eq!(
+            outcome,
+            KaryOutcome {
+                index: 437,
+                rounds: 3
+            }
+        );
+
+        let outcome = bisect_kary(0..1000, 2, |probes| {
+            probes.iter().map(|&i| i < 437).collect()
+        });
+        assert_eq!(outcome.index, 437);
+        assert_eq!(outcome.rounds, 10);
+    }
+
+    #[test]
+    fn empty_range() {
+        let outcome = bisect_kary(5..5, 4, |_| unreachable!());
+        assert_eq!(
+            outcome,
+            KaryOutcome {
+                index: 5,
+                rounds: 0
+            }
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_bisect(
+            mut nums in prop::collection::vec(0..100_u32, 0..500),
+            num in 0..100_u32,
+            k in 2..16_usize
+        ) {
+            nums.sort();
+
+            let left = bisect_kary(0..nums.len(), k, |probes| {
+                assert!(probes.len() < k);
+                probes.iter().map(|&i| nums[i] < num).collect()
+            });
+            prop_assert_eq!(left.index, bisect_left(&nums, &num));
+
+            let right = bisect_kary(0..nums.len(), k, |probes| {
+                probes.iter().map(|&i| nums[i] <= num).collect()
+            });
+            prop_assert_eq!(right.index, bisect_right(&nums, &num));
+        }
+    }
+}
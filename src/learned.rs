@@ -0,0 +1,344 @@
+//! Learned index over sorted `u64` keys.
+//!
+//! A piecewise linear model maps keys to approximate positions in the sorted keys, with every
+//! key predicted to within a configurable error bound. Lookups predict a position and then
+//! bisect only the small window of keys around it. The model is typically orders of magnitude
+//! smaller than the keys themselves and does not own them, so it can be built once, persisted
+//! and used with the keys wherever they live.
+
+use crate::{bisect_left_slice, bisect_right_by, bisect_right_slice};
+use std::io::{self, Read, Write};
+use std::mem;
+use std::ops::Range;
+
+const MAGIC: &[u8; 4] = b"BSLI";
+const VERSION: u8 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Segment {
+    /// First key covered by the segment.
+    key: u64,
+    slope: f64,
+    /// Predicted position of `key`.
+    intercept: f64,
+}
+
+impl Segment {
+    fn predict(&self, x: u64) -> f64 {
+        self.intercept + self.slope * (x - self.key) as f64
+    }
+}
+
+/// Piecewise linear model of the positions of sorted `u64` keys.
+///
+/// The index answers `bisect_left`/`bisect_right` queries against the keys it was built from,
+/// which must be passed to each query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LearnedIndex {
+    segments: Vec<Segment>,
+    len: usize,
+    epsilon: usize,
+    max_error: usize,
+}
+
+impl LearnedIndex {
+    /// Fit a model to `keys`, assuming they are sorted, predicting the positions of the first and
+    /// last occurrences of each distinct key to within roughly `epsilon`.
+    ///
+    /// Larger values of `epsilon` produce smaller models at the cost of bisecting larger windows.
+    /// Runs of more than about `2 * epsilon` equal keys cannot be predicted that closely, and
+    /// widen the error bound to about half their length.
+    pub fn new(keys: &[u64], epsilon: usize) -> Self {
+        let eps = epsilon as f64;
+        let mut segments = vec![];
+        // Start of the current segment and the range of slopes that keep it within `epsilon`.
+        let mut start: Option<(u64, f64)> = None;
+        let (mut min_slope, mut max_slope) = (0.0, f64::INFINITY);
+
+        for (key, first, last) in runs(keys) {
+            // Predictions for `key` must lie within `epsilon` of both `first` and `last`, or as
+            // close as possible to both if they are too far apart.
+            let (lo, hi) = (last as f64 - eps, first as f64 + eps);
+            let (lo, hi) = if lo <= hi {
+                (lo, hi)
+            } else {
+                let mid = (first + last) as f64 / 2.0;
+                (mid, mid)
+            };
+            if let Some((first_key, intercept)) = start {
+                let dx = (key - first_key) as f64;
+                let lo = f64::max(min_slope, (lo - intercept) / dx);
+                let hi = f64::min(max_slope, (hi - intercept) / dx);
+                if lo <= hi {
+                    min_slope = lo;
+                    max_slope = hi;
+                    continue;
+                }
+                segments.push(segment(first_key, intercept, min_slope, max_slope));
+            }
+            start = Some((key, (first as f64).max(lo).min(hi)));
+            min_slope = 0.0;
+            max_slope = f64::INFINITY;
+        }
+        if let Some((first_key, intercept)) = start {
+            segments.push(segment(first_key, intercept, min_slope, max_slope));
+        }
+
+        let mut index = LearnedIndex {
+            segments,
+            len: keys.len(),
+            epsilon,
+            max_error: 0,
+        };
+        // Rounding may push a few predictions slightly past `epsilon`, so measure the real error.
+        index.max_error = runs(keys)
+            .map(|(key, first, last)| {
+                let pred = index.predict(key);
+                f64::max(pred - first as f64, last as f64 - pred).ceil() as usize
+            })
+            .max()
+            .unwrap_or(0);
+        index
+    }
+
+    /// Return the number of keys the index was built from.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return `true` if the index was built from no keys.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return the error bound the model was fitted with.
+    pub fn epsilon(&self) -> usize {
+        self.epsilon
+    }
+
+    /// Return the largest distance between the predicted position of any key and its first or
+    /// last occurrence.
+    pub fn max_error(&self) -> usize {
+        self.max_error
+    }
+
+    /// Return the number of linear segments in the model.
+    pub fn segments(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Return the size of the model in bytes, excluding the keys.
+    pub fn size_in_bytes(&self) -> usize {
+        mem::size_of::<Self>() + self.segments.len() * mem::size_of::<Segment>()
+    }
+
+    /// Return the window of positions in which the model predicts `x` to be inserted.
+    ///
+    /// The position where `x` should be inserted is always within the window or at its end.
+    pub fn search_window(&self, x: u64) -> Range<usize> {
+        let pred = self.predict(x);
+        let lo = (pred.floor() as usize).saturating_sub(self.max_error);
+        let hi = (pred.ceil() as usize).saturating_add(self.max_error + 1);
+        lo.min(self.len)..hi.min(self.len)
+    }
+
+    /// Return the index where `x` should be inserted in `keys`.
+    ///
+    /// Equivalent to [`bisect_left`](crate::bisect_left), provided `keys` are the keys the index
+    /// was built from.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `keys` is not the same length as the keys the index was built from.
+    pub fn bisect_left(&self, keys: &[u64], x: u64) -> usize {
+        assert_eq!(keys.len(), self.len, "keys do not match the index");
+        bisect_left_slice(keys, &x, self.search_window(x))
+    }
+
+    /// Return the index where `x` should be inserted in `keys`.
+    ///
+    /// Equivalent to [`bisect_right`](crate::bisect_right), provided `keys` are the keys the
+    /// index was built from.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `keys` is not the same length as the keys the index was built from.
+    pub fn bisect_right(&self, keys: &[u64], x: u64) -> usize {
+        match x.checked_add(1) {
+            Some(next) => self.bisect_left(keys, next),
+            None => {
+                assert_eq!(keys.len(), self.len, "keys do not match the index");
+                self.len
+            }
+        }
+    }
+
+    /// Serialize the model to `w`.
+    pub fn write_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        for n in &[self.len, self.epsilon, self.max_error, self.segments.len()] {
+            w.write_all(&(*n as u64).to_le_bytes())?;
+        }
+        for s in &self.segments {
+            w.write_all(&s.key.to_le_bytes())?;
+            w.write_all(&s.slope.to_bits().to_le_bytes())?;
+            w.write_all(&s.intercept.to_bits().to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Deserialize a model previously written by [`write_to`](LearnedIndex::write_to).
+    pub fn read_from<R: Read>(mut r: R) -> io::Result<Self> {
+        let mut header = [0; 5];
+        r.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a learned index",
+            ));
+        }
+        let len = read_u64(&mut r)? as usize;
+        let epsilon = read_u64(&mut r)? as usize;
+        let max_error = read_u64(&mut r)? as usize;
+        let count = read_u64(&mut r)?;
+        let mut segments = vec![];
+        for _ in 0..count {
+            segments.push(Segment {
+                key: read_u64(&mut r)?,
+                slope: f64::from_bits(read_u64(&mut r)?),
+                intercept: f64::from_bits(read_u64(&mut r)?),
+            });
+        }
+        Ok(LearnedIndex {
+            segments,
+            len,
+            epsilon,
+            max_error,
+        })
+    }
+
+    fn predict(&self, x: u64) -> f64 {
+        let i = bisect_right_by(&self.segments, |s| x.cmp(&s.key));
+        if i == 0 {
+            return 0.0;
+        }
+        let pred = self.segments[i - 1].predict(x);
+        // Never predict past the start of the next segment.
+        let limit = self
+            .segments
+            .get(i)
+            .map_or(self.len as f64, |s| s.intercept);
+        pred.max(0.0).min(limit)
+    }
+}
+
+fn segment(key: u64, intercept: f64, min_slope: f64, max_slope: f64) -> Segment {
+    let slope = if max_slope.is_finite() {
+        (min_slope + max_slope) / 2.0
+    } else {
+        min_slope
+    };
+    Segment {
+        key,
+        slope,
+        intercept,
+    }
+}
+
+/// Iterate over each distinct key of `keys` along with the positions of its first and last
+/// occurrences.
+fn runs(keys: &[u64]) -> impl Iterator<Item = (u64, usize, usize)> + '_ {
+    keys.iter()
+        .enumerate()
+        .filter(move |&(i, k)| i == 0 || keys[i - 1] != *k)
+        .map(move |(i, &k)| (k, i, bisect_right_slice(keys, &k, i..) - 1))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bisect_left, bisect_right};
+    use proptest::prelude::*;
+
+    #[test]
+    fn empty() {
+        let index = LearnedIndex::new(&[], 4);
+        assert!(index.is_empty());
+        assert_eq!(index.segments(), 0);
+        assert_eq!(index.bisect_left(&[], 3), 0);
+        assert_eq!(index.bisect_right(&[], u64::MAX), 0);
+    }
+
+    #[test]
+    fn linear_keys_fit_one_segment() {
+        let keys: Vec<u64> = (0..10_000).map(|i| 1_000 + 7 * i).collect();
+        let index = LearnedIndex::new(&keys, 2);
+        assert_eq!(index.segments(), 1);
+        assert!(index.max_error() <= 2);
+        for x in 990..1_200 {
+            assert_eq!(index.bisect_left(&keys, x), bisect_left(&keys, &x));
+            assert_eq!(index.bisect_right(&keys, x), bisect_right(&keys, &x));
+        }
+    }
+
+    #[test]
+    fn duplicates_stay_in_window() {
+        // Runs of up to 30 equal keys, which the model cannot tell apart.
+        let keys: Vec<u64> = (0..2_000_u64)
+            .flat_map(|k| std::iter::repeat_n(k * 3, (k * 7 % 30 + 1) as usize))
+            .collect();
+        let index = LearnedIndex::new(&keys, 16);
+        assert!(index.max_error() <= 17);
+        for x in 0..6_002 {
+            let window = index.search_window(x);
+            assert!(window.len() <= 2 * index.max_error() + 2);
+            let expected = bisect_left(&keys, &x);
+            assert!(window.start <= expected && expected <= window.end, "{}", x);
+            assert_eq!(index.bisect_left(&keys, x), expected);
+        }
+    }
+
+    #[test]
+    fn serialization_round_trip() {
+        let keys: Vec<u64> = (0..1_000).map(|i: u64| i * i).collect();
+        let index = LearnedIndex::new(&keys, 8);
+
+        let mut buf = vec![];
+        index.write_to(&mut buf).unwrap();
+        assert_eq!(LearnedIndex::read_from(&buf[..]).unwrap(), index);
+
+        buf[0] = b'X';
+        assert!(LearnedIndex::read_from(&buf[..]).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_bisect(
+            mut keys in prop::collection::vec(any::<u64>(), 0..500),
+            dups in prop::collection::vec(0..1000_u64, 0..200),
+            x in any::<u64>(),
+            epsilon in 0..32_usize
+        ) {
+            keys.extend(dups);
+            keys.sort();
+            let index = LearnedIndex::new(&keys, epsilon);
+
+            let longest_run = keys.chunk_by(|a, b| a == b).map(<[u64]>::len).max().unwrap_or(0);
+            prop_assert!(index.max_error() <= epsilon.max(longest_run / 2) + 1);
+            for &x in keys.iter().chain(&[x, 0, 500, u64::MAX]) {
+                let window = index.search_window(x);
+                let expected = bisect_left(&keys, &x);
+                prop_assert!(window.start <= expected && expected <= window.end);
+                prop_assert_eq!(index.bisect_left(&keys, x), bisect_left(&keys, &x));
+                prop_assert_eq!(index.bisect_right(&keys, x), bisect_right(&keys, &x));
+            }
+        }
+    }
+}
@@ -1,6 +1,29 @@
 pub use crate::bisect_right as bisect;
 pub use crate::insort_right as insort;
 
+pub mod asynchronous;
+pub mod bisector;
+pub mod btree;
+pub mod column;
+pub mod compare;
+pub mod elias_fano;
+pub mod eytzinger;
+pub mod file;
+pub mod interpolation;
+pub mod kary;
+pub mod learned;
+pub mod logseek;
+pub mod matrix;
+pub mod noisy;
+pub mod prefix;
+pub mod record;
+pub mod rotated;
+pub mod segmented;
+pub mod sorted_list;
+pub mod sparse;
+pub mod trace;
+pub mod unimodal;
+
 use std::cmp::Ordering;
 use std::ops::{Bound::*, RangeBounds};
 
@@ -130,21 +153,12 @@ where
 /// # Panics
 ///
 /// Panics if `within` is out of bounds of `a`.
-pub fn bisect_right_slice_by<T, I, F>(a: &[T], within: I, mut f: F) -> usize
+pub fn bisect_right_slice_by<T, I, F>(a: &[T], within: I, f: F) -> usize
 where
     I: RangeBounds<usize>,
     F: FnMut(&T) -> Ordering,
 {
-    let (mut lo, mut hi) = bounds_to_indices(a, within);
-    while lo < hi {
-        let mid = (lo + hi) / 2;
-        if f(&a[mid]) == Ordering::Less {
-            hi = mid;
-        } else {
-            lo = mid + 1;
-        }
-    }
-    lo
+    trace::bisect_right_slice_by_observed(a, within, f, ())
 }
 
 /// Insert `x` in `a[within]`, keeping it sorted assuming `a` is sorted.
@@ -271,22 +285,12 @@ where
 /// # Panics
 ///
 /// Panics if `within` is out of bounds of `a`.
-pub fn bisect_left_slice_by<T, I, F>(a: &[T], within: I, mut f: F) -> usize
+pub fn bisect_left_slice_by<T, I, F>(a: &[T], within: I, f: F) -> usize
 where
     I: RangeBounds<usize>,
     F: FnMut(&T) -> Ordering,
 {
-    let (mut lo, mut hi) = bounds_to_indices(a, within);
-    while lo < hi {
-        let mid = (lo + hi) / 2;
-        let cmp = f(&a[mid]);
-        if cmp == Ordering::Less {
-            lo = mid + 1;
-        } else {
-            hi = mid;
-        }
-    }
-    lo
+    trace::bisect_left_slice_by_observed(a, within, f, ())
 }
 
 /// Convert bounds to a `(lo, hi)`  pair for indexing into a slice of `a`.
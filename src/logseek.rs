@@ -0,0 +1,368 @@
+//! Bisection of chronologically ordered log files by timestamp.
+//!
+//! Each line is passed to a parser which returns its timestamp, or `None` for lines without one,
+//! such as the continuation lines of a multi-line stack trace. Such lines belong to the entry
+//! started by the last timestamped line before them, so positions returned here are always the
+//! start of a timestamped line or the end of the data. Lines before the first timestamped line
+//! are treated as earlier than any time.
+//!
+//! [`rfc3339`] and [`unix_epoch`] parse the most common formats; any other format can be
+//! supported with a closure.
+
+use crate::file::LineReader;
+use std::io::{self, Read, Seek};
+use std::ops::Range;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Return the offset of the first entry of `reader` with a timestamp not before `time`,
+/// assuming the entries are in chronological order.
+///
+/// This follows the semantics of [`bisect_left`](crate::bisect_left).
+pub fn bisect_left<R, P>(reader: &mut R, time: SystemTime, parser: P) -> io::Result<u64>
+where
+    R: Read + Seek + ?Sized,
+    P: FnMut(&[u8]) -> Option<SystemTime>,
+{
+    let mut lines = LineReader::new(reader);
+    let within = lines.resolve(..)?;
+    partition_point(&mut lines, within, parser, |t| t < time)
+}
+
+/// Return the offset of the first entry of `reader` with a timestamp after `time`, assuming the
+/// entries are in chronological order.
+///
+/// This follows the semantics of [`bisect_right`](crate::bisect_right).
+pub fn bisect_right<R, P>(reader: &mut R, time: SystemTime, parser: P) -> io::Result<u64>
+where
+    R: Read + Seek + ?Sized,
+    P: FnMut(&[u8]) -> Option<SystemTime>,
+{
+    let mut lines = LineReader::new(reader);
+    let within = lines.resolve(..)?;
+    partition_point(&mut lines, within, parser, |t| t <= time)
+}
+
+/// Return the bytes of `reader` holding the entries with timestamps in `start..end`, assuming the
+/// entries are in chronological order.
+pub fn time_range<R, P>(
+    reader: &mut R,
+    start: SystemTime,
+    end: SystemTime,
+    mut parser: P,
+) -> io::Result<Range<u64>>
+where
+    R: Read + Seek + ?Sized,
+    P: FnMut(&[u8]) -> Option<SystemTime>,
+{
+    let mut lines = LineReader::new(reader);
+    let within = lines.resolve(..)?;
+    let lo = partition_point(&mut lines, within.clone(), &mut parser, |t| t < start)?;
+    let hi = partition_point(&mut lines, lo..within.end, &mut parser, |t| t < end)?;
+    Ok(lo..hi.max(lo))
+}
+
+/// Return the offset of the first timestamped line in `within` whose timestamp is not
+/// `before`, or the end of `within` if there is none.
+fn partition_point<R, P, B>(
+    lines: &mut LineReader<'_, R>,
+    within: Range<u64>,
+    mut parser: P,
+    mut before: B,
+) -> io::Result<u64>
+where
+    R: Read + Seek + ?Sized,
+    P: FnMut(&[u8]) -> Option<SystemTime>,
+    B: FnMut(SystemTime) -> bool,
+{
+    // Invariant: `lo` is the start of a line, all timestamped lines starting before `lo` are
+    // before the boundary and all starting at or after `hi` are not.
+    let (mut lo, mut hi) = (within.start, within.end);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let start = if mid == lo {
+            lo
+        } else {
+            lines.line_start_from(mid, hi)?
+        };
+        match next_timestamped(lines, start, hi, within.end, &mut parser)? {
+            // No timestamped line starts in `mid..hi`.
+            None => hi = mid,
+            Some((_, next, time)) if before(time) => lo = next,
+            Some((start, _, _)) => hi = start,
+        }
+    }
+    // Lines from `lo` up to the next timestamped line continue an entry before the boundary.
+    let boundary = next_timestamped(lines, lo, within.end, within.end, &mut parser)?;
+    Ok(boundary.map_or(within.end, |(start, _, _)| start))
+}
+
+/// Find the first timestamped line starting in `from..to`, returning its start, the start of the
+/// next line and its timestamp.
+fn next_timestamped<R, P>(
+    lines: &mut LineReader<'_, R>,
+    from: u64,
+    to: u64,
+    end: u64,
+    parser: &mut P,
+) -> io::Result<Option<(u64, u64, SystemTime)>>
+where
+    R: Read + Seek + ?Sized,
+    P: FnMut(&[u8]) -> Option<SystemTime>,
+{
+    let mut start = from;
+    while start < to {
+        let next = lines.read_line(start, end)?;
+        if let Some(time) = parser(lines.line()) {
+            return Ok(Some((start, next, time)));
+        }
+        start = next;
+    }
+    Ok(None)
+}
+
+/// Parse an RFC 3339 timestamp at the start of `line`, such as `2024-05-01T14:03:59.123Z`.
+///
+/// An opening `[` before the timestamp is skipped, the date and time may be separated by a
+/// space, and a missing UTC offset is taken to mean UTC.
+pub fn rfc3339(line: &[u8]) -> Option<SystemTime> {
+    let mut s = Scanner(line);
+    s.eat(b'[');
+    let year = s.digits(4, 4)? as i64;
+    s.expect(b'-')?;
+    let month = s.digits(2, 2)?;
+    s.expect(b'-')?;
+    let day = s.digits(2, 2)?;
+    if !(s.eat(b'T') || s.eat(b't') || s.eat(b' ')) {
+        return None;
+    }
+    let hour = s.digits(2, 2)?;
+    s.expect(b':')?;
+    let minute = s.digits(2, 2)?;
+    s.expect(b':')?;
+    let second = s.digits(2, 2)?;
+    let nanos = if s.eat(b'.') { s.fraction()? } else { 0 };
+
+    let offset = if s.eat(b'Z') || s.eat(b'z') {
+        0
+    } else if let Some(sign) = s.sign() {
+        let h = s.digits(2, 2)?;
+        s.expect(b':')?;
+        let m = s.digits(2, 2)?;
+        sign * (h * 3600 + m * 60) as i64
+    } else {
+        0
+    };
+
+    if !(1..=12).contains(&month)
+        || day == 0
+        || day > days_in_month(year, month)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    let secs = days * 86_400 + (hour * 3600 + minute * 60 + second) as i64 - offset;
+    let time = if secs >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))?
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))?
+    };
+    time.checked_add(Duration::from_nanos(nanos))
+}
+
+/// Parse a number of seconds since the Unix epoch, with an optional fraction, at the start of
+/// `line`, such as `1714572239.123`.
+pub fn unix_epoch(line: &[u8]) -> Option<SystemTime> {
+    let mut s = Scanner(line);
+    let secs = s.digits(1, 20)?;
+    let nanos = if s.eat(b'.') { s.fraction()? } else { 0 };
+    UNIX_EPOCH.checked_add(Duration::new(secs, nanos as u32))
+}
+
+/// Cursor over the bytes of a timestamp.
+struct Scanner<'a>(&'a [u8]);
+
+impl Scanner<'_> {
+    fn eat(&mut self, b: u8) -> bool {
+        match self.0.split_first() {
+            Some((&first, rest)) if first == b => {
+                self.0 = rest;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, b: u8) -> Option<()> {
+        self.eat(b).then_some(())
+    }
+
+    fn sign(&mut self) -> Option<i64> {
+        if self.eat(b'+') {
+            Some(1)
+        } else if self.eat(b'-') {
+            Some(-1)
+        } else {
+            None
+        }
+    }
+
+    /// Parse between `min` and `max` decimal digits, not followed by another digit.
+    fn digits(&mut self, min: usize, max: usize) -> Option<u64> {
+        let n = self.0.iter().take_while(|b| b.is_ascii_digit()).count();
+        if n < min || n > max {
+            return None;
+        }
+        let (digits, rest) = self.0.split_at(n);
+        self.0 = rest;
+        digits.iter().try_fold(0u64, |acc, b| {
+            acc.checked_mul(10)?.checked_add((b - b'0') as u64)
+        })
+    }
+
+    /// Parse the digits of a decimal fraction of a second as nanoseconds, ignoring digits beyond
+    /// the ninth.
+    fn fraction(&mut self) -> Option<u64> {
+        let n = self.0.iter().take_while(|b| b.is_ascii_digit()).count();
+        if n == 0 {
+            return None;
+        }
+        let nanos = self.0[..n]
+            .iter()
+            .chain(std::iter::repeat(&b'0'))
+            .take(9)
+            .fold(0, |acc, b| acc * 10 + (b - b'0') as u64);
+        self.0 = &self.0[n..];
+        Some(nanos)
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u64) -> u64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Return the number of days from 1970-01-01 to the given date in the proleptic Gregorian
+/// calendar.
+fn days_from_civil(year: i64, month: u64, day: u64) -> i64 {
+    // Count from 0000-03-01 so that the leap day ends each 4, 100 and 400 year cycle.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::io::Cursor;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn parsers() {
+        assert_eq!(rfc3339(b"1970-01-01T00:00:00Z"), Some(at(0)));
+        assert_eq!(
+            rfc3339(b"2024-05-01T14:03:59Z GET /"),
+            Some(at(1_714_572_239))
+        );
+        assert_eq!(
+            rfc3339(b"[2024-05-01 16:03:59.5+02:00] x"),
+            Some(at(1_714_572_239) + Duration::from_millis(500))
+        );
+        assert_eq!(rfc3339(b"2024-02-29T00:00:00"), Some(at(1_709_164_800)));
+        assert_eq!(
+            rfc3339(b"1969-12-31T23:59:59Z"),
+            UNIX_EPOCH.checked_sub(Duration::from_secs(1))
+        );
+        assert_eq!(rfc3339(b"2023-02-29T00:00:00Z"), None);
+        assert_eq!(rfc3339(b"2024-05-01T24:00:00Z"), None);
+        assert_eq!(rfc3339(b"    at com.example.Main"), None);
+
+        assert_eq!(unix_epoch(b"1714572239 GET /"), Some(at(1_714_572_239)));
+        assert_eq!(
+            unix_epoch(b"12.000000001999"),
+            Some(at(12) + Duration::from_nanos(1))
+        );
+        assert_eq!(unix_epoch(b"GET /"), None);
+    }
+
+    #[test]
+    fn multi_line_entries() {
+        let log = b"  orphan\n\
+            10 start\n\
+            20 error\n  at a\n  at b\n\
+            20 retry\n\
+            30 done\n  at c";
+        let mut r = Cursor::new(&log[..]);
+
+        assert_eq!(bisect_left(&mut r, at(0), unix_epoch).unwrap(), 9);
+        assert_eq!(bisect_left(&mut r, at(20), unix_epoch).unwrap(), 18);
+        assert_eq!(bisect_right(&mut r, at(20), unix_epoch).unwrap(), 50);
+        assert_eq!(bisect_left(&mut r, at(25), unix_epoch).unwrap(), 50);
+        assert_eq!(bisect_right(&mut r, at(30), unix_epoch).unwrap(), 64);
+        assert_eq!(
+            time_range(&mut r, at(15), at(30), unix_epoch).unwrap(),
+            18..50
+        );
+        assert_eq!(
+            time_range(&mut r, at(30), at(15), unix_epoch).unwrap(),
+            50..50
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_linear_scan(
+            entries in prop::collection::vec((0..20_u64, 0..3_usize), 0..50),
+            orphans in 0..2_usize,
+            start in 0..21_u64,
+            len in 0..10_u64
+        ) {
+            let mut times: Vec<u64> = entries.iter().map(|e| e.0).collect();
+            times.sort();
+            let mut log = "  orphan\n".repeat(orphans);
+            // Offset and timestamp of each entry.
+            let mut starts = vec![];
+            for (time, (_, continuations)) in times.iter().zip(&entries) {
+                starts.push((log.len() as u64, *time));
+                log.push_str(&format!("{} entry\n", time));
+                log.push_str(&"  at x\n".repeat(*continuations));
+            }
+            let end = log.len() as u64;
+            let first = |pred: &dyn Fn(u64) -> bool| {
+                starts.iter().find(|&&(_, t)| pred(t)).map_or(end, |&(o, _)| o)
+            };
+
+            let mut r = Cursor::new(log.as_bytes());
+            prop_assert_eq!(
+                bisect_left(&mut r, at(start), unix_epoch).unwrap(),
+                first(&|t| t >= start)
+            );
+            prop_assert_eq!(
+                bisect_right(&mut r, at(start), unix_epoch).unwrap(),
+                first(&|t| t > start)
+            );
+            prop_assert_eq!(
+                time_range(&mut r, at(start), at(start + len), unix_epoch).unwrap(),
+                first(&|t| t >= start)..first(&|t| t >= start + len)
+            );
+        }
+    }
+}
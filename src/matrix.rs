@@ -0,0 +1,187 @@
+//! Search in matrices whose rows and columns are both sorted.
+//!
+//! Matrices are given as a row-major slice along with their dimensions. Starting from a corner,
+//! a "staircase" walk moves along a row or down a column at each step, so membership and counts
+//! take `O(rows + cols)` comparisons. Counts follow the crate's left and right conventions:
+//! [`count_less_than`] sums [`bisect_left`](crate::bisect_left) over the rows and
+//! [`count_less_equal`] sums [`bisect_right`](crate::bisect_right).
+
+use crate::{bisect_left_slice, bisect_right_slice};
+
+/// Return `true` if the `rows` by `cols` matrix `a` contains `x`.
+///
+/// # Panics
+///
+/// Panics if `a` does not have `rows * cols` elements.
+pub fn contains<T: Ord>(a: &[T], rows: usize, cols: usize, x: &T) -> bool {
+    check_dimensions(a, rows, cols);
+    // Walk from the top right corner: everything left of it in its row is smaller and
+    // everything below it in its column is larger.
+    let (mut row, mut col) = (0, cols);
+    while row < rows && col > 0 {
+        let e = &a[row * cols + col - 1];
+        if e == x {
+            return true;
+        } else if e > x {
+            col -= 1;
+        } else {
+            row += 1;
+        }
+    }
+    false
+}
+
+/// Return the number of elements `e` of the `rows` by `cols` matrix `a` with `e < x`.
+///
+/// # Panics
+///
+/// Panics if `a` does not have `rows * cols` elements.
+pub fn count_less_than<T: Ord>(a: &[T], rows: usize, cols: usize, x: &T) -> usize {
+    count_before(a, rows, cols, |e| e < x)
+}
+
+/// Return the number of elements `e` of the `rows` by `cols` matrix `a` with `e <= x`.
+///
+/// # Panics
+///
+/// Panics if `a` does not have `rows * cols` elements.
+pub fn count_less_equal<T: Ord>(a: &[T], rows: usize, cols: usize, x: &T) -> usize {
+    count_before(a, rows, cols, |e| e <= x)
+}
+
+/// Return the `k`th smallest element, counting from zero, of the `rows` by `cols` matrix `a`, or
+/// `None` if `k` is out of bounds.
+///
+/// The search bisects on values taken from the matrix itself, so `T` only needs to be `Ord`.
+///
+/// # Panics
+///
+/// Panics if `a` does not have `rows * cols` elements.
+pub fn kth_smallest<T: Ord>(a: &[T], rows: usize, cols: usize, k: usize) -> Option<&T> {
+    check_dimensions(a, rows, cols);
+    if k >= a.len() {
+        return None;
+    }
+    let row = |r: usize| &a[r * cols..(r + 1) * cols];
+    // The answer is in `row(r)[lo[r]..hi[r]]` for some row `r`.
+    let mut lo = vec![0; rows];
+    let mut hi = vec![cols; rows];
+    loop {
+        // Taking the pivot from the middle of the widest window at least halves that window.
+        let r = (0..rows).max_by_key(|&r| hi[r] - lo[r])?;
+        let pivot = &row(r)[(lo[r] + hi[r]) / 2];
+        if k < count_less_than(a, rows, cols, pivot) {
+            for r in 0..rows {
+                hi[r] = bisect_left_slice(row(r), pivot, lo[r]..hi[r]);
+            }
+        } else if k >= count_less_equal(a, rows, cols, pivot) {
+            for r in 0..rows {
+                lo[r] = bisect_right_slice(row(r), pivot, lo[r]..hi[r]);
+            }
+        } else {
+            return Some(pivot);
+        }
+    }
+}
+
+/// Count the elements `e` of the matrix with `before(e)`, assuming `before` holds for a prefix
+/// of every row and column.
+fn count_before<T, F>(a: &[T], rows: usize, cols: usize, mut before: F) -> usize
+where
+    F: FnMut(&T) -> bool,
+{
+    check_dimensions(a, rows, cols);
+    // The prefix of each row satisfying `before` is no longer than that of the row above.
+    let mut col = cols;
+    let mut count = 0;
+    for row in 0..rows {
+        while col > 0 && !before(&a[row * cols + col - 1]) {
+            col -= 1;
+        }
+        count += col;
+    }
+    count
+}
+
+fn check_dimensions<T>(a: &[T], rows: usize, cols: usize) {
+    assert_eq!(
+        Some(a.len()),
+        rows.checked_mul(cols),
+        "matrix dimensions do not match its length"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const GRID: [i32; 12] = [
+        1, 4, 7, 11, //
+        2, 5, 8, 12, //
+        3, 6, 9, 16, //
+    ];
+
+    #[test]
+    fn staircase() {
+        assert!(contains(&GRID, 3, 4, &5));
+        assert!(contains(&GRID, 3, 4, &16));
+        assert!(!contains(&GRID, 3, 4, &10));
+        assert!(!contains::<i32>(&[], 0, 4, &10));
+
+        assert_eq!(count_less_than(&GRID, 3, 4, &6), 5);
+        assert_eq!(count_less_equal(&GRID, 3, 4, &6), 6);
+        assert_eq!(count_less_than(&GRID, 3, 4, &0), 0);
+        assert_eq!(count_less_equal(&GRID, 3, 4, &100), 12);
+
+        assert_eq!(kth_smallest(&GRID, 3, 4, 0), Some(&1));
+        assert_eq!(kth_smallest(&GRID, 3, 4, 6), Some(&7));
+        assert_eq!(kth_smallest(&GRID, 3, 4, 11), Some(&16));
+        assert_eq!(kth_smallest(&GRID, 3, 4, 12), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_dimensions() {
+        contains(&GRID, 4, 4, &1);
+    }
+
+    /// Build a matrix with sorted rows and columns from arbitrary values: the sum of a sorted row
+    /// vector and a sorted column vector.
+    fn arb_matrix() -> impl Strategy<Value = (Vec<u32>, usize, usize)> {
+        (
+            prop::collection::vec(0..50_u32, 0..12),
+            prop::collection::vec(0..50_u32, 0..12),
+        )
+            .prop_map(|(mut r, mut c)| {
+                r.sort();
+                c.sort();
+                let a = r
+                    .iter()
+                    .flat_map(|x| c.iter().map(move |y| x + y))
+                    .collect();
+                (a, r.len(), c.len())
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_sorted(
+            (a, rows, cols) in arb_matrix(),
+            x in 0..101_u32
+        ) {
+            let mut sorted = a.clone();
+            sorted.sort();
+
+            prop_assert_eq!(contains(&a, rows, cols, &x), sorted.contains(&x));
+            prop_assert_eq!(count_less_than(&a, rows, cols, &x), crate::bisect_left(&sorted, &x));
+            prop_assert_eq!(
+                count_less_equal(&a, rows, cols, &x),
+                crate::bisect_right(&sorted, &x)
+            );
+            for k in 0..=sorted.len() {
+                prop_assert_eq!(kth_smallest(&a, rows, cols, k), sorted.get(k));
+            }
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! Probabilistic bisection for unreliable predicates.
+//!
+//! When the predicate is a flaky test, a single wrong answer sends an ordinary bisection off in
+//! the wrong direction for good. Instead, [`NoisySession`] keeps a probability for every possible
+//! boundary position, updates them by Bayes' rule after each observation given the rates at which
+//! the predicate is known to lie, and always probes the median of that distribution. The same
+//! index may be probed many times; the search stops once one position is believed with the
+//! requested confidence.
+
+use crate::bisector::Verdict;
+use std::ops::Range;
+
+/// Most likely position of the boundary and the probability that it is correct.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    /// The first bad index, or the end of the range if every index is good.
+    pub index: usize,
+    /// Probability that `index` is the boundary, given the observations so far.
+    pub probability: f64,
+}
+
+/// State of a probabilistic bisection for the first bad index of a range.
+///
+/// As with [`Session`](crate::bisector::Session), indices are assumed to be good up to some
+/// boundary and bad from there on, and the search is driven with
+/// [`next_probe`](NoisySession::next_probe) and [`mark`](NoisySession::mark).
+#[derive(Clone, Debug)]
+pub struct NoisySession {
+    start: usize,
+    /// `posterior[k]` is the probability that the boundary is at `start + k`.
+    posterior: Vec<f64>,
+    false_positive: f64,
+    false_negative: f64,
+    confidence: f64,
+    probes: usize,
+}
+
+impl NoisySession {
+    /// Start a session searching for the first bad index in `range`.
+    ///
+    /// `false_positive` is the probability that the predicate reports a good index as bad, and
+    /// `false_negative` that it reports a bad index as good. The search is done once the most
+    /// likely boundary has at least probability `confidence`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either error rate is not in `0.0..0.5`, if both are zero, or if `confidence` is
+    /// not in `0.0..=1.0`. A predicate which never errs should be bisected with
+    /// [`Session`](crate::bisector::Session) instead.
+    pub fn new(
+        range: Range<usize>,
+        false_positive: f64,
+        false_negative: f64,
+        confidence: f64,
+    ) -> Self {
+        assert!(
+            (0.0..0.5).contains(&false_positive) && (0.0..0.5).contains(&false_negative),
+            "error rates must be in 0.0..0.5"
+        );
+        assert!(
+            false_positive > 0.0 || false_negative > 0.0,
+            "error rates must not both be zero"
+        );
+        assert!(
+            (0.0..=1.0).contains(&confidence),
+            "confidence must be in 0.0..=1.0"
+        );
+        let positions = range.end.saturating_sub(range.start) + 1;
+        NoisySession {
+            start: range.start,
+            posterior: vec![1.0 / positions as f64; positions],
+            false_positive,
+            false_negative,
+            confidence,
+            probes: 0,
+        }
+    }
+
+    /// Return the index to evaluate next, or `None` if the search is done.
+    ///
+    /// This is the index splitting the probability of the boundary most evenly in two.
+    pub fn next_probe(&self) -> Option<usize> {
+        if self.is_done() {
+            return None;
+        }
+        let mut below = 0.0;
+        let mut best = (f64::INFINITY, 0);
+        // Probing index `start + k` asks whether the boundary is at or before it.
+        for (k, p) in self.posterior[..self.posterior.len() - 1]
+            .iter()
+            .enumerate()
+        {
+            below += p;
+            let imbalance = (below - 0.5).abs();
+            if imbalance < best.0 {
+                best = (imbalance, k);
+            }
+        }
+        Some(self.start + best.1)
+    }
+
+    /// Record an observation of the predicate at `index`, updating the probabilities.
+    ///
+    /// `Skip` verdicts carry no information and are ignored, as are observations so unlikely
+    /// that every probability would underflow to zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is outside of the searched range.
+    pub fn mark(&mut self, index: usize, verdict: Verdict) {
+        assert!(
+            self.start <= index && index < self.start + self.posterior.len() - 1,
+            "index out of bounds"
+        );
+        let i = index - self.start;
+        // Likelihood of the observation if the boundary is at or before `index` (so `index` is
+        // bad) and if it is after `index` (so `index` is good).
+        let (if_bad, if_good) = match verdict {
+            Verdict::Bad => (1.0 - self.false_negative, self.false_positive),
+            Verdict::Good => (self.false_negative, 1.0 - self.false_positive),
+            Verdict::Skip => return,
+        };
+        let likelihood = |k: usize| if k <= i { if_bad } else { if_good };
+        let total: f64 = (self.posterior.iter().enumerate())
+            .map(|(k, p)| p * likelihood(k))
+            .sum();
+        if total <= 0.0 {
+            return;
+        }
+        for (k, p) in self.posterior.iter_mut().enumerate() {
+            *p *= likelihood(k) / total;
+        }
+        self.probes += 1;
+    }
+
+    /// Return `true` once the most likely boundary has reached the requested confidence.
+    pub fn is_done(&self) -> bool {
+        self.posterior.len() == 1 || self.estimate().probability >= self.confidence
+    }
+
+    /// Return the most likely position of the boundary.
+    pub fn estimate(&self) -> Estimate {
+        let (k, &probability) = self
+            .posterior
+            .iter()
+            .enumerate()
+            .fold((0, &0.0), |best, p| if p.1 > best.1 { p } else { best });
+        Estimate {
+            index: self.start + k,
+            probability,
+        }
+    }
+
+    /// Return the probability that the boundary is at `index`.
+    pub fn probability(&self, index: usize) -> f64 {
+        index
+            .checked_sub(self.start)
+            .and_then(|k| self.posterior.get(k))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Return the number of informative observations made so far.
+    pub fn probes(&self) -> usize {
+        self.probes
+    }
+
+    /// Run the search until it is done or `max_probes` evaluations of `f` have been made,
+    /// returning the best estimate of the boundary.
+    pub fn run<F>(&mut self, max_probes: usize, mut f: F) -> Estimate
+    where
+        F: FnMut(usize) -> Verdict,
+    {
+        for _ in 0..max_probes {
+            match self.next_probe() {
+                Some(index) => self.mark(index, f(index)),
+                None => break,
+            }
+        }
+        self.estimate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic flaky predicate: reports the wrong answer with probability `error`.
+    fn flaky(boundary: usize, error: f64, mut seed: u64) -> impl FnMut(usize) -> Verdict {
+        move |i| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let lie = ((seed >> 11) as f64 / (1u64 << 53) as f64) < error;
+            if (i >= boundary) != lie {
+                Verdict::Bad
+            } else {
+                Verdict::Good
+            }
+        }
+    }
+
+    #[test]
+    fn empty_range() {
+        let mut session = NoisySession::new(5..5, 0.1, 0.1, 0.99);
+        assert!(session.is_done());
+        assert_eq!(session.run(10, |_| unreachable!()).index, 5);
+    }
+
+    #[test]
+    fn reliable_predicate() {
+        let mut session = NoisySession::new(0..100, 0.01, 0.01, 0.999);
+        let estimate = session.run(1000, flaky(37, 0.0, 1));
+        assert_eq!(estimate.index, 37);
+        assert!(estimate.probability >= 0.999);
+    }
+
+    #[test]
+    fn flaky_predicate() {
+        for (seed, &boundary) in [0, 1, 50, 99, 100].iter().enumerate() {
+            let mut session = NoisySession::new(0..100, 0.2, 0.2, 0.99);
+            let estimate = session.run(1000, flaky(boundary, 0.2, seed as u64));
+            assert_eq!(estimate.index, boundary);
+            assert!(session.is_done());
+            assert!(session.probability(boundary) >= 0.99);
+        }
+    }
+
+    #[test]
+    fn contradictions() {
+        let mut session = NoisySession::new(0..10, 0.01, 0.01, 0.99);
+        session.mark(5, Verdict::Good);
+        session.mark(3, Verdict::Bad);
+        assert_eq!(session.probes(), 2);
+        let total: f64 = (0..=10).map(|i| session.probability(i)).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        let estimate = session.estimate();
+        assert!(estimate.probability > 0.0 && estimate.probability < 0.99);
+        assert!(session.next_probe().is_some());
+
+        // Observations repeated often enough settle the contradiction.
+        let estimate = session.run(1000, |i| if i >= 4 { Verdict::Bad } else { Verdict::Good });
+        assert_eq!(estimate.index, 4);
+    }
+
+    #[test]
+    fn one_sided_errors() {
+        // Good indices are sometimes reported bad, but bad ones are never reported good.
+        let mut session = NoisySession::new(0..100, 0.2, 0.0, 0.99);
+        let mut lies = flaky(usize::MAX, 0.2, 7);
+        let estimate = session.run(1000, |i| if i >= 63 { Verdict::Bad } else { lies(i) });
+        assert_eq!(estimate.index, 63);
+
+        // Contradicting a zero error rate leaves the session usable.
+        let mut session = NoisySession::new(0..10, 0.1, 0.0, 0.99);
+        session.mark(5, Verdict::Bad);
+        session.mark(5, Verdict::Good);
+        assert!(session.estimate().probability.is_finite());
+        assert!(session.next_probe().is_some());
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_error_rates() {
+        NoisySession::new(0..10, 0.0, 0.0, 0.99);
+    }
+
+    #[test]
+    fn skips_are_ignored() {
+        let mut session = NoisySession::new(0..10, 0.1, 0.1, 0.9);
+        session.mark(3, Verdict::Skip);
+        assert_eq!(session.probes(), 0);
+        assert_eq!(session.probability(3), 1.0 / 11.0);
+    }
+}
@@ -0,0 +1,144 @@
+//! Prefix queries over sorted strings and byte strings.
+//!
+//! Entries are compared bytewise, which for `String` and `&str` is the same as their `Ord`
+//! implementation, so slices sorted with `sort()` can be searched directly. Autocompletion
+//! reduces to [`prefix_range`] and routing table lookups to [`longest_prefix_match`].
+
+use crate::{bisect_left_by, bisect_right_by};
+use std::ops::Range;
+
+/// Return the range of entries of `a` starting with `prefix`, assuming `a` is sorted.
+///
+/// The range starts where `prefix` would be inserted and ends where its [`successor`] would be,
+/// so it is empty, at the position where `prefix` would be inserted, if no entry matches.
+pub fn prefix_range<S, P>(a: &[S], prefix: P) -> Range<usize>
+where
+    S: AsRef<[u8]>,
+    P: AsRef<[u8]>,
+{
+    let prefix = prefix.as_ref();
+    let start = bisect_left_by(a, |e| e.as_ref().cmp(prefix));
+    let end = match successor(prefix) {
+        Some(upper) => bisect_left_by(&a[start..], |e| e.as_ref().cmp(&upper[..])) + start,
+        None => a.len(),
+    };
+    start..end
+}
+
+/// Return the smallest byte string greater than every string starting with `prefix`, or `None`
+/// if there is none because `prefix` consists only of `0xFF` bytes.
+///
+/// Trailing `0xFF` bytes are dropped and the last remaining byte incremented. The result need
+/// not be valid UTF-8 even if `prefix` is, but compares correctly with UTF-8 strings bytewise.
+pub fn successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|&b| b != 0xFF)?;
+    let mut upper = prefix[..=last].to_vec();
+    upper[last] += 1;
+    Some(upper)
+}
+
+/// Return the index of the longest entry of `a` which is a prefix of `key`, assuming `a` is
+/// sorted.
+///
+/// If several entries are equal to that prefix, the last of them is returned.
+pub fn longest_prefix_match<S, K>(a: &[S], key: K) -> Option<usize>
+where
+    S: AsRef<[u8]>,
+    K: AsRef<[u8]>,
+{
+    let mut key = key.as_ref();
+    let mut end = a.len();
+    loop {
+        // The greatest entry not greater than `key`. Any prefix of `key` longer than the part
+        // it shares with `key` would lie between it and `key`, so only shorter prefixes remain.
+        end = bisect_right_by(&a[..end], |e| key.cmp(e.as_ref()));
+        let entry = a[end.checked_sub(1)?].as_ref();
+        let common = entry.iter().zip(key).take_while(|(x, y)| x == y).count();
+        if common == entry.len() {
+            return Some(end - 1);
+        }
+        key = &key[..common];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn successors() {
+        assert_eq!(successor(b"abc"), Some(b"abd".to_vec()));
+        assert_eq!(successor(b"a\xff\xff"), Some(b"b".to_vec()));
+        assert_eq!(successor(b"\xff"), None);
+        assert_eq!(successor(b""), None);
+    }
+
+    #[test]
+    fn high_bytes() {
+        let entries: Vec<Vec<u8>> = vec![
+            b"a".to_vec(),
+            b"a\xff".to_vec(),
+            b"a\xff\x00".to_vec(),
+            b"a\xff\xff".to_vec(),
+            b"b".to_vec(),
+            b"\xff".to_vec(),
+            b"\xff\xff".to_vec(),
+        ];
+        assert_eq!(prefix_range(&entries, b"a\xff"), 1..4);
+        assert_eq!(prefix_range(&entries, b"\xff"), 5..7);
+        assert_eq!(prefix_range(&entries, b""), 0..7);
+    }
+
+    #[test]
+    fn unicode() {
+        let mut words = vec!["naïve", "naive", "nab", "nañ", "nb", "日本", "日本語"];
+        words.sort();
+        assert_eq!(prefix_range(&words, "na").len(), 4);
+        assert_eq!(prefix_range(&words, "nc"), 5..5);
+        assert_eq!(&words[prefix_range(&words, "日本")], ["日本", "日本語"]);
+        let owned: Vec<String> = words.iter().map(|w| w.to_string()).collect();
+        assert_eq!(prefix_range(&owned, "nai"), prefix_range(&words, "nai"));
+    }
+
+    #[test]
+    fn routes() {
+        let routes = ["10.", "10.1.", "10.1.2.", "10.10.", "10.2."];
+        assert_eq!(longest_prefix_match(&routes, "10.1.2.3"), Some(2));
+        assert_eq!(longest_prefix_match(&["/", "/api/"], "/api/v1"), Some(1));
+        assert_eq!(longest_prefix_match(&routes, "10.1.9.9"), Some(1));
+        // "10.1.2." lies between "10.1." and "10.1.3" without being a prefix of it.
+        assert_eq!(longest_prefix_match(&routes, "10.1.3"), Some(1));
+        assert_eq!(longest_prefix_match(&routes, "10.3.0.1"), Some(0));
+        assert_eq!(longest_prefix_match(&routes, "1"), None);
+        assert_eq!(longest_prefix_match::<&str, _>(&[], "10."), None);
+    }
+
+    /// Short byte strings over an alphabet including the extremes.
+    fn bytes(max_len: usize) -> impl Strategy<Value = Vec<u8>> {
+        prop::collection::vec(prop::sample::select(vec![0, 1, 0xFE, 0xFF]), 0..max_len)
+    }
+
+    proptest! {
+        #[test]
+        fn test_prefix_range(
+            mut entries in prop::collection::vec(bytes(5), 0..50),
+            prefix in bytes(4)
+        ) {
+            entries.sort();
+            let found = prefix_range(&entries, &prefix);
+            for (i, e) in entries.iter().enumerate() {
+                prop_assert_eq!(found.contains(&i), e.starts_with(&prefix));
+            }
+            prop_assert_eq!(found.start, crate::bisect_left(&entries, &prefix));
+
+            let expected = entries
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| prefix.starts_with(e))
+                .max_by_key(|&(i, e)| (e.len(), i))
+                .map(|(i, _)| i);
+            prop_assert_eq!(longest_prefix_match(&entries, &prefix), expected);
+        }
+    }
+}
@@ -0,0 +1,548 @@
+//! Bisection over sorted fixed-size binary records.
+//!
+//! Records are `record_size` consecutive bytes, either in a seekable source ([`RecordFile`]) or
+//! in memory ([`RecordSlice`]), sorted by a key decoded from each record by a closure. Searches
+//! return record indices with the same semantics as the crate's slice functions, so index `i`
+//! starts at byte `i * record_size`.
+
+use std::cmp::Ordering;
+use std::convert::Infallible;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::{Bound::*, Range, RangeBounds};
+
+/// Sorted fixed-size records in a seekable source.
+#[derive(Debug)]
+pub struct RecordFile<R, K> {
+    inner: R,
+    record_size: usize,
+    len: u64,
+    key: K,
+    buf: Vec<u8>,
+}
+
+impl<R, K, T> RecordFile<R, K>
+where
+    R: Read + Seek,
+    K: FnMut(&[u8]) -> T,
+    T: Ord,
+{
+    /// Return the index where a record with key `x` should be inserted, before any records with
+    /// an equal key.
+    pub fn bisect_left(&mut self, x: &T) -> io::Result<u64> {
+        let key = &mut self.key;
+        partition_point(&mut self.inner, &mut self.buf, 0..self.len, |r| key(r) < *x)
+    }
+
+    /// Return the index where a record with key `x` should be inserted, after any records with
+    /// an equal key.
+    pub fn bisect_right(&mut self, x: &T) -> io::Result<u64> {
+        let key = &mut self.key;
+        partition_point(&mut self.inner, &mut self.buf, 0..self.len, |r| {
+            key(r) <= *x
+        })
+    }
+
+    /// Return the indices of the records with key `x`.
+    pub fn equal_range(&mut self, x: &T) -> io::Result<Range<u64>> {
+        let start = self.bisect_left(x)?;
+        let key = &mut self.key;
+        let end = partition_point(&mut self.inner, &mut self.buf, start..self.len, |r| {
+            key(r) <= *x
+        })?;
+        Ok(start..end)
+    }
+}
+
+impl<R: Read + Seek, K> RecordFile<R, K> {
+    /// Create a searcher over the records of `inner`, assuming they are sorted by `key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `InvalidInput` if `record_size` is zero, of kind `InvalidData`
+    /// if the length of `inner` is not a multiple of `record_size`, and any error from seeking
+    /// `inner`.
+    pub fn new(mut inner: R, record_size: usize, key: K) -> io::Result<Self> {
+        if record_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "record size must be positive",
+            ));
+        }
+        let bytes = inner.seek(SeekFrom::End(0))?;
+        if bytes % record_size as u64 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "length is not a multiple of the record size",
+            ));
+        }
+        Ok(RecordFile {
+            inner,
+            record_size,
+            len: bytes / record_size as u64,
+            key,
+            buf: vec![0; record_size],
+        })
+    }
+
+    /// Return the number of records.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Return `true` if there are no records.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return the size of each record in bytes.
+    pub fn record_size(&self) -> usize {
+        self.record_size
+    }
+
+    /// Return the underlying source.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Read the record at `index`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `InvalidInput` if `index` is out of bounds, and any error from
+    /// reading or seeking the source.
+    pub fn get(&mut self, index: u64) -> io::Result<&[u8]> {
+        if index >= self.len {
+            return Err(out_of_bounds());
+        }
+        read_record(&mut self.inner, &mut self.buf, index)?;
+        Ok(&self.buf)
+    }
+
+    /// Return the index where a record should be inserted, according to a comparator function
+    /// called with whole records.
+    ///
+    /// The comparator function follows the same contract as
+    /// [`bisect_left_by`](crate::bisect_left_by).
+    pub fn bisect_left_by<F>(&mut self, f: F) -> io::Result<u64>
+    where
+        F: FnMut(&[u8]) -> Ordering,
+    {
+        self.bisect_left_slice_by(.., f)
+    }
+
+    /// Return the index where a record should be inserted, according to a comparator function
+    /// called with whole records.
+    ///
+    /// The comparator function follows the same contract as
+    /// [`bisect_right_by`](crate::bisect_right_by).
+    pub fn bisect_right_by<F>(&mut self, f: F) -> io::Result<u64>
+    where
+        F: FnMut(&[u8]) -> Ordering,
+    {
+        self.bisect_right_slice_by(.., f)
+    }
+
+    /// Return the index where a record should be inserted among the records `within`,
+    /// according to a comparator function called with whole records.
+    ///
+    /// The comparator function follows the same contract as
+    /// [`bisect_left_by`](crate::bisect_left_by).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `InvalidInput` if `within` is out of bounds, and any error from
+    /// reading or seeking the source.
+    pub fn bisect_left_slice_by<I, F>(&mut self, within: I, mut f: F) -> io::Result<u64>
+    where
+        I: RangeBounds<u64>,
+        F: FnMut(&[u8]) -> Ordering,
+    {
+        let within = resolve(within, self.len).ok_or_else(out_of_bounds)?;
+        partition_point(&mut self.inner, &mut self.buf, within, |r| {
+            f(r) == Ordering::Less
+        })
+    }
+
+    /// Return the index where a record should be inserted among the records `within`,
+    /// according to a comparator function called with whole records.
+    ///
+    /// The comparator function follows the same contract as
+    /// [`bisect_right_by`](crate::bisect_right_by).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `InvalidInput` if `within` is out of bounds, and any error from
+    /// reading or seeking the source.
+    pub fn bisect_right_slice_by<I, F>(&mut self, within: I, mut f: F) -> io::Result<u64>
+    where
+        I: RangeBounds<u64>,
+        F: FnMut(&[u8]) -> Ordering,
+    {
+        let within = resolve(within, self.len).ok_or_else(out_of_bounds)?;
+        partition_point(&mut self.inner, &mut self.buf, within, |r| {
+            f(r) != Ordering::Less
+        })
+    }
+
+    /// Return an iterator over the records from `index` to the end.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `InvalidInput` if `index` is out of bounds, and any error from
+    /// seeking the source.
+    pub fn iter_from(&mut self, index: u64) -> io::Result<Records<'_, R>> {
+        if index > self.len {
+            return Err(out_of_bounds());
+        }
+        self.inner
+            .seek(SeekFrom::Start(index * self.record_size as u64))?;
+        Ok(Records {
+            inner: &mut self.inner,
+            record_size: self.record_size,
+            remaining: self.len - index,
+        })
+    }
+}
+
+/// Iterator over the records of a [`RecordFile`], reading them in order.
+#[derive(Debug)]
+pub struct Records<'a, R> {
+    inner: &'a mut R,
+    record_size: usize,
+    remaining: u64,
+}
+
+impl<'a, R: Read> Iterator for Records<'a, R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let mut record = vec![0; self.record_size];
+        match self.inner.read_exact(&mut record) {
+            Ok(()) => self.remaining -= 1,
+            // Stop after the first error.
+            Err(e) => {
+                self.remaining = 0;
+                return Some(Err(e));
+            }
+        }
+        Some(Ok(record))
+    }
+}
+
+/// Sorted fixed-size records in memory.
+#[derive(Clone, Copy, Debug)]
+pub struct RecordSlice<'a, K> {
+    data: &'a [u8],
+    record_size: usize,
+    key: K,
+}
+
+impl<'a, K, T> RecordSlice<'a, K>
+where
+    K: Fn(&[u8]) -> T,
+    T: Ord,
+{
+    /// Return the index where a record with key `x` should be inserted, before any records with
+    /// an equal key.
+    pub fn bisect_left(&self, x: &T) -> usize {
+        self.bisect_left_by(|r| (self.key)(r).cmp(x))
+    }
+
+    /// Return the index where a record with key `x` should be inserted, after any records with
+    /// an equal key.
+    pub fn bisect_right(&self, x: &T) -> usize {
+        self.bisect_right_by(|r| x.cmp(&(self.key)(r)))
+    }
+
+    /// Return the indices of the records with key `x`.
+    pub fn equal_range(&self, x: &T) -> Range<usize> {
+        let start = self.bisect_left(x);
+        let end = self.bisect_right_slice_by(start.., |r| x.cmp(&(self.key)(r)));
+        start..end
+    }
+}
+
+impl<'a, K> RecordSlice<'a, K> {
+    /// Create a searcher over the records of `data`, assuming they are sorted by `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `record_size` is zero or the length of `data` is not a multiple of it.
+    pub fn new(data: &'a [u8], record_size: usize, key: K) -> Self {
+        assert!(record_size > 0, "record size must be positive");
+        assert_eq!(
+            data.len() % record_size,
+            0,
+            "length is not a multiple of the record size"
+        );
+        RecordSlice {
+            data,
+            record_size,
+            key,
+        }
+    }
+
+    /// Return the number of records.
+    pub fn len(&self) -> usize {
+        self.data.len() / self.record_size
+    }
+
+    /// Return `true` if there are no records.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Return the size of each record in bytes.
+    pub fn record_size(&self) -> usize {
+        self.record_size
+    }
+
+    /// Return the record at `index`, or `None` if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> Option<&'a [u8]> {
+        let start = index.checked_mul(self.record_size)?;
+        let end = start.checked_add(self.record_size)?;
+        self.data.get(start..end)
+    }
+
+    /// Return the index where a record should be inserted, according to a comparator function
+    /// called with whole records.
+    ///
+    /// The comparator function follows the same contract as
+    /// [`bisect_left_by`](crate::bisect_left_by).
+    pub fn bisect_left_by<F>(&self, f: F) -> usize
+    where
+        F: FnMut(&[u8]) -> Ordering,
+    {
+        self.bisect_left_slice_by(.., f)
+    }
+
+    /// Return the index where a record should be inserted, according to a comparator function
+    /// called with whole records.
+    ///
+    /// The comparator function follows the same contract as
+    /// [`bisect_right_by`](crate::bisect_right_by).
+    pub fn bisect_right_by<F>(&self, f: F) -> usize
+    where
+        F: FnMut(&[u8]) -> Ordering,
+    {
+        self.bisect_right_slice_by(.., f)
+    }
+
+    /// Return the index where a record should be inserted among the records `within`,
+    /// according to a comparator function called with whole records.
+    ///
+    /// The comparator function follows the same contract as
+    /// [`bisect_left_by`](crate::bisect_left_by).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `within` is out of bounds.
+    pub fn bisect_left_slice_by<I, F>(&self, within: I, mut f: F) -> usize
+    where
+        I: RangeBounds<usize>,
+        F: FnMut(&[u8]) -> Ordering,
+    {
+        self.partition_point(within, |r| f(r) == Ordering::Less)
+    }
+
+    /// Return the index where a record should be inserted among the records `within`,
+    /// according to a comparator function called with whole records.
+    ///
+    /// The comparator function follows the same contract as
+    /// [`bisect_right_by`](crate::bisect_right_by).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `within` is out of bounds.
+    pub fn bisect_right_slice_by<I, F>(&self, within: I, mut f: F) -> usize
+    where
+        I: RangeBounds<usize>,
+        F: FnMut(&[u8]) -> Ordering,
+    {
+        self.partition_point(within, |r| f(r) != Ordering::Less)
+    }
+
+    /// Return an iterator over the records from `index` to the end.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn iter_from(&self, index: usize) -> std::slice::ChunksExact<'a, u8> {
+        self.data[index * self.record_size..].chunks_exact(self.record_size)
+    }
+
+    fn partition_point<I, P>(&self, within: I, mut before: P) -> usize
+    where
+        I: RangeBounds<usize>,
+        P: FnMut(&[u8]) -> bool,
+    {
+        let len = self.len() as u64;
+        let bounds = (
+            within.start_bound().map(|&i| i as u64),
+            within.end_bound().map(|&i| i as u64),
+        );
+        let within = resolve(bounds, len).expect("index out of bounds");
+        let i = generic_partition_point(within, |i| {
+            Ok::<_, Infallible>(before(self.get(i as usize).unwrap()))
+        });
+        match i {
+            Ok(i) => i as usize,
+            Err(e) => match e {},
+        }
+    }
+}
+
+/// Convert bounds to a range of indices, or `None` if they are out of bounds of `len` records.
+fn resolve<I: RangeBounds<u64>>(within: I, len: u64) -> Option<Range<u64>> {
+    let lo = match within.start_bound() {
+        Unbounded => 0,
+        Included(i) => *i,
+        Excluded(i) => i.checked_add(1)?,
+    };
+    let hi = match within.end_bound() {
+        Unbounded => len,
+        Included(i) => i.checked_add(1)?,
+        Excluded(i) => *i,
+    };
+    if lo <= hi && hi <= len {
+        Some(lo..hi)
+    } else {
+        None
+    }
+}
+
+fn out_of_bounds() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "record index out of bounds")
+}
+
+fn read_record<R: Read + Seek>(inner: &mut R, buf: &mut [u8], index: u64) -> io::Result<()> {
+    inner.seek(SeekFrom::Start(index * buf.len() as u64))?;
+    inner.read_exact(buf)
+}
+
+/// Return the index of the first record in `within` for which `before` is `false`, reading
+/// records into `buf`.
+fn partition_point<R, P>(
+    inner: &mut R,
+    buf: &mut [u8],
+    within: Range<u64>,
+    mut before: P,
+) -> io::Result<u64>
+where
+    R: Read + Seek,
+    P: FnMut(&[u8]) -> bool,
+{
+    generic_partition_point(within, |i| {
+        read_record(inner, buf, i)?;
+        Ok(before(buf))
+    })
+}
+
+/// Return the first index in `within` for which `before` is `false`, stopping at the first
+/// error.
+fn generic_partition_point<P, E>(within: Range<u64>, mut before: P) -> Result<u64, E>
+where
+    P: FnMut(u64) -> Result<bool, E>,
+{
+    let (mut lo, mut hi) = (within.start, within.end);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if before(mid)? {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    Ok(lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::io::Cursor;
+
+    /// Records of a big-endian `u32` key followed by a `u32` payload.
+    fn records(keys: &[u32]) -> Vec<u8> {
+        keys.iter()
+            .enumerate()
+            .flat_map(|(i, k)| {
+                let mut r = k.to_be_bytes().to_vec();
+                r.extend_from_slice(&(i as u32).to_le_bytes());
+                r
+            })
+            .collect()
+    }
+
+    fn key(r: &[u8]) -> u32 {
+        u32::from_be_bytes([r[0], r[1], r[2], r[3]])
+    }
+
+    #[test]
+    fn file() {
+        let data = records(&[1, 3, 3, 3, 7, 9]);
+        let mut f = RecordFile::new(Cursor::new(&data), 8, key).unwrap();
+        assert_eq!(f.len(), 6);
+        assert_eq!(f.bisect_left(&3).unwrap(), 1);
+        assert_eq!(f.bisect_right(&3).unwrap(), 4);
+        assert_eq!(f.equal_range(&3).unwrap(), 1..4);
+        assert_eq!(f.equal_range(&8).unwrap(), 5..5);
+        assert_eq!(key(f.get(4).unwrap()), 7);
+        assert!(f.get(6).is_err());
+
+        let payloads: Vec<u8> = f.iter_from(3).unwrap().map(|r| r.unwrap()[4]).collect();
+        assert_eq!(payloads, [3, 4, 5]);
+        assert_eq!(f.iter_from(6).unwrap().count(), 0);
+
+        let overflow = f.bisect_left_slice_by(..=u64::MAX, |r| key(r).cmp(&3));
+        assert_eq!(overflow.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        let overflow = f.bisect_right_slice_by((Excluded(u64::MAX), Unbounded), |r| 3.cmp(&key(r)));
+        assert_eq!(overflow.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        // Comparators over whole records need no key.
+        let mut raw = RecordFile::new(Cursor::new(&data), 8, ()).unwrap();
+        assert_eq!(raw.bisect_right_by(|r| 3.cmp(&key(r))).unwrap(), 4);
+
+        let bad = RecordFile::new(Cursor::new(&data[1..]), 8, key)
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(bad.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn slice() {
+        let data = records(&[1, 3, 3, 3, 7, 9]);
+        let s = RecordSlice::new(&data, 8, key);
+        assert_eq!(s.len(), 6);
+        assert_eq!(s.equal_range(&3), 1..4);
+        assert_eq!(s.bisect_left_slice_by(2.., |r| key(r).cmp(&0)), 2);
+        assert_eq!(s.get(6), None);
+        // The record would end past `usize::MAX`.
+        assert_eq!(s.get(usize::MAX / 8), None);
+        assert_eq!(s.iter_from(4).map(key).collect::<Vec<_>>(), [7, 9]);
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_slice_functions(
+            mut keys in prop::collection::vec(0..50_u32, 0..200),
+            x in 0..51_u32
+        ) {
+            keys.sort();
+            let data = records(&keys);
+            let left = crate::bisect_left(&keys, &x);
+            let right = crate::bisect_right(&keys, &x);
+
+            let mut f = RecordFile::new(Cursor::new(&data), 8, key).unwrap();
+            prop_assert_eq!(f.equal_range(&x).unwrap(), left as u64..right as u64);
+            prop_assert_eq!(f.bisect_right_by(|r| x.cmp(&key(r))).unwrap(), right as u64);
+
+            let s = RecordSlice::new(&data, 8, key);
+            prop_assert_eq!(s.bisect_left(&x), left);
+            prop_assert_eq!(s.bisect_right(&x), right);
+        }
+    }
+}
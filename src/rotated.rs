@@ -0,0 +1,168 @@
+//! Search in rotated sorted slices.
+//!
+//! A rotated sorted slice is a sorted slice which has been split in two and had its halves
+//! swapped, as produced by flattening a ring buffer. Its *logical* order starts at the rotation
+//! point, the index of its first smallest element, and wraps around: `a[r..]` followed by
+//! `a[..r]`. Positions returned by the `bisect_*_rotated` functions are indices into this logical
+//! order, so logical position `i` is at physical index `(r + i) % a.len()`.
+
+use crate::{bisect_left_slice_by, bisect_right_slice_by};
+use std::cmp::Ordering;
+
+/// Return the index at which the logical order of `a` starts, assuming `a` is a rotated sorted
+/// slice.
+///
+/// This takes `O(log n)` comparisons, degrading towards `O(n)` when `a` contains many elements
+/// equal to its endpoints.
+pub fn find_rotation_point<T: Ord>(a: &[T]) -> usize {
+    find_rotation_point_by(a, T::cmp)
+}
+
+/// Return the index at which the logical order of `a` starts, assuming `a` is a rotated sorted
+/// slice, according to a comparator function.
+///
+/// The comparator function should implement an order consistent with the sort order of the
+/// rotated slice.
+pub fn find_rotation_point_by<T, F>(a: &[T], mut f: F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    if a.is_empty() {
+        return 0;
+    }
+    // Invariant: the rotation point is in `lo..=hi`.
+    let (mut lo, mut hi) = (0, a.len() - 1);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        match f(&a[mid], &a[hi]) {
+            Ordering::Greater => lo = mid + 1,
+            Ordering::Less => hi = mid,
+            Ordering::Equal => {
+                // Cannot tell which half `mid` is in, but `hi` can only be the rotation point if
+                // it follows a larger element.
+                if f(&a[hi - 1], &a[hi]) == Ordering::Greater {
+                    return hi;
+                }
+                hi -= 1;
+            }
+        }
+    }
+    lo
+}
+
+/// Return the logical position where `x` should be inserted in `a`, assuming `a` is a rotated
+/// sorted slice.
+///
+/// The return value `i` is such that all `e` in the first `i` elements of the logical order
+/// have `e < x`, and all other elements have `e >= x`.
+pub fn bisect_left_rotated<T: Ord>(a: &[T], x: &T) -> usize {
+    bisect_left_rotated_by(a, x, T::cmp)
+}
+
+/// Return the logical position where `x` should be inserted in `a`, assuming `a` is a rotated
+/// sorted slice.
+///
+/// The return value `i` is such that all `e` in the first `i` elements of the logical order
+/// have `e <= x`, and all other elements have `e > x`.
+pub fn bisect_right_rotated<T: Ord>(a: &[T], x: &T) -> usize {
+    bisect_right_rotated_by(a, x, T::cmp)
+}
+
+/// Return the logical position where `x` should be inserted in `a`, assuming `a` is a rotated
+/// sorted slice, according to a comparator function.
+///
+/// The comparator function should implement an order consistent with the sort order of the
+/// rotated slice.
+///
+/// If `a` contains `x`, `x` would be inserted just *before* the *leftmost* occurence of `x`.
+pub fn bisect_left_rotated_by<T, F>(a: &[T], x: &T, mut f: F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let r = find_rotation_point_by(a, &mut f);
+    let i = bisect_left_slice_by(a, r.., |p| f(p, x));
+    if i < a.len() {
+        i - r
+    } else {
+        (a.len() - r) + bisect_left_slice_by(a, ..r, |p| f(p, x))
+    }
+}
+
+/// Return the logical position where `x` should be inserted in `a`, assuming `a` is a rotated
+/// sorted slice, according to a comparator function.
+///
+/// The comparator function should implement an order consistent with the sort order of the
+/// rotated slice.
+///
+/// If `a` contains `x`, `x` would be inserted just *after* the *rightmost* occurence of `x`.
+pub fn bisect_right_rotated_by<T, F>(a: &[T], x: &T, mut f: F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let r = find_rotation_point_by(a, &mut f);
+    let i = bisect_right_slice_by(a, r.., |p| f(x, p));
+    if i < a.len() {
+        i - r
+    } else {
+        (a.len() - r) + bisect_right_slice_by(a, ..r, |p| f(x, p))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bisect_left, bisect_right};
+    use proptest::prelude::*;
+
+    #[test]
+    fn rotation_point() {
+        assert_eq!(find_rotation_point::<u32>(&[]), 0);
+        assert_eq!(find_rotation_point(&[1]), 0);
+        assert_eq!(find_rotation_point(&[4, 5, 6, 1, 2, 3]), 3);
+        assert_eq!(find_rotation_point(&[1, 2, 3]), 0);
+        assert_eq!(find_rotation_point(&[2, 2, 2]), 0);
+        assert_eq!(find_rotation_point(&[0, 1, 0]), 2);
+        assert_eq!(find_rotation_point(&[1, 1, 0, 1]), 2);
+        assert_eq!(find_rotation_point(&[1, 1, 1, 1, 0]), 4);
+    }
+
+    #[test]
+    fn logical_positions() {
+        let a = [7, 8, 9, 1, 2, 2, 5];
+        assert_eq!(bisect_left_rotated(&a, &2), 1);
+        assert_eq!(bisect_right_rotated(&a, &2), 3);
+        assert_eq!(bisect_left_rotated(&a, &8), 5);
+        assert_eq!(bisect_right_rotated(&a, &10), 7);
+        assert_eq!(bisect_left_rotated(&a, &0), 0);
+    }
+
+    #[test]
+    fn by_comparator() {
+        // Sorted by descending order, then rotated.
+        let a = [3, 2, 1, 9, 7, 5];
+        let desc = |x: &i32, y: &i32| y.cmp(x);
+        assert_eq!(find_rotation_point_by(&a, desc), 3);
+        assert_eq!(bisect_left_rotated_by(&a, &5, desc), 2);
+        assert_eq!(bisect_right_rotated_by(&a, &5, desc), 3);
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_bisect_on_sorted(
+            mut nums in prop::collection::vec(0..20_u32, 0..200),
+            num in 0..21_u32,
+            rotation in any::<prop::sample::Index>()
+        ) {
+            nums.sort();
+            let mut rotated = nums.clone();
+            if !nums.is_empty() {
+                rotated.rotate_left(rotation.index(nums.len()));
+            }
+
+            let r = find_rotation_point(&rotated);
+            prop_assert!(rotated[r..].iter().chain(&rotated[..r]).eq(nums.iter()));
+            prop_assert_eq!(bisect_left_rotated(&rotated, &num), bisect_left(&nums, &num));
+            prop_assert_eq!(bisect_right_rotated(&rotated, &num), bisect_right(&nums, &num));
+        }
+    }
+}
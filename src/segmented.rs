@@ -0,0 +1,288 @@
+//! Bisection over a sorted sequence stored as a list of sorted segments.
+//!
+//! Large sorted datasets are often kept as a list of bounded-size chunks, each sorted and every
+//! chunk ordered after the one before it, so that inserting only shifts the elements of one
+//! chunk. These functions treat such segments as one logical sorted sequence: they first bisect
+//! on the last (largest) element of each segment to find the segment, then bisect within it.
+//!
+//! Segments must be non-empty; the insort functions maintain this.
+
+use crate::{bisect_left_slice_by, bisect_right_slice_by};
+use std::cmp::Ordering;
+
+/// Location of an element in a list of segments.
+///
+/// For insertion points past the last element this is the end of the last segment, or segment
+/// `0` if there are no segments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    /// Index of the segment.
+    pub segment: usize,
+    /// Index within the segment.
+    pub offset: usize,
+    /// Index within the whole logical sequence.
+    pub index: usize,
+}
+
+/// Return the position where `x` should be inserted in `segments`, assuming they are sorted.
+///
+/// The position has the same `index` as [`bisect_left`](crate::bisect_left) on the
+/// concatenation of the segments.
+///
+/// # Panics
+///
+/// Panics if a segment probed by the search is empty.
+pub fn bisect_left<T: Ord>(segments: &[Vec<T>], x: &T) -> Position {
+    bisect_left_by(segments, |p| p.cmp(x))
+}
+
+/// Return the position where `x` should be inserted in `segments`, assuming they are sorted.
+///
+/// The position has the same `index` as [`bisect_right`](crate::bisect_right) on the
+/// concatenation of the segments.
+///
+/// # Panics
+///
+/// Panics if a segment probed by the search is empty.
+pub fn bisect_right<T: Ord>(segments: &[Vec<T>], x: &T) -> Position {
+    bisect_right_by(segments, |p| x.cmp(p))
+}
+
+/// Return the position where a value should be inserted in `segments`, assuming they are sorted,
+/// according to a comparator function.
+///
+/// The comparator function follows the same contract as
+/// [`bisect_left_by`](crate::bisect_left_by).
+///
+/// # Panics
+///
+/// Panics if a segment probed by the search is empty.
+pub fn bisect_left_by<T, F>(segments: &[Vec<T>], f: F) -> Position
+where
+    F: FnMut(&T) -> Ordering,
+{
+    let (segment, offset) = locate_left_by(segments, f);
+    position(segments, segment, offset)
+}
+
+/// Return the position where a value should be inserted in `segments`, assuming they are sorted,
+/// according to a comparator function.
+///
+/// The comparator function follows the same contract as
+/// [`bisect_right_by`](crate::bisect_right_by).
+///
+/// # Panics
+///
+/// Panics if a segment probed by the search is empty.
+pub fn bisect_right_by<T, F>(segments: &[Vec<T>], f: F) -> Position
+where
+    F: FnMut(&T) -> Ordering,
+{
+    let (segment, offset) = locate_right_by(segments, f);
+    position(segments, segment, offset)
+}
+
+/// Insert `x` in `segments`, keeping them sorted assuming they are sorted, and return its
+/// position.
+///
+/// If `segments` contains `x`, insert it just *before* the *leftmost* occurence of `x`. A
+/// segment growing beyond `max_len` elements is split in two.
+///
+/// # Panics
+///
+/// Panics if `max_len` is zero.
+pub fn insort_left<T: Ord>(segments: &mut Vec<Vec<T>>, x: T, max_len: usize) -> Position {
+    insort_left_by(segments, x, max_len, T::cmp)
+}
+
+/// Insert `x` in `segments`, keeping them sorted assuming they are sorted, and return its
+/// position.
+///
+/// If `segments` contains `x`, insert it just *after* the *rightmost* occurence of `x`. A
+/// segment growing beyond `max_len` elements is split in two.
+///
+/// # Panics
+///
+/// Panics if `max_len` is zero.
+pub fn insort_right<T: Ord>(segments: &mut Vec<Vec<T>>, x: T, max_len: usize) -> Position {
+    insort_right_by(segments, x, max_len, T::cmp)
+}
+
+/// Insert `x` in `segments`, keeping them sorted assuming they are sorted, according to a
+/// comparator function, and return its position.
+///
+/// If `segments` contains `x`, insert it just *before* the *leftmost* occurence of `x`. A
+/// segment growing beyond `max_len` elements is split in two.
+///
+/// # Panics
+///
+/// Panics if `max_len` is zero.
+pub fn insort_left_by<T, F>(segments: &mut Vec<Vec<T>>, x: T, max_len: usize, mut f: F) -> Position
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let (segment, offset) = locate_left_by(segments, |p| f(p, &x));
+    insert(segments, segment, offset, x, max_len)
+}
+
+/// Insert `x` in `segments`, keeping them sorted assuming they are sorted, according to a
+/// comparator function, and return its position.
+///
+/// If `segments` contains `x`, insert it just *after* the *rightmost* occurence of `x`. A
+/// segment growing beyond `max_len` elements is split in two.
+///
+/// # Panics
+///
+/// Panics if `max_len` is zero.
+pub fn insort_right_by<T, F>(segments: &mut Vec<Vec<T>>, x: T, max_len: usize, mut f: F) -> Position
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let (segment, offset) = locate_right_by(segments, |p| f(&x, p));
+    insert(segments, segment, offset, x, max_len)
+}
+
+/// Return the `(segment, offset)` where a value should be inserted, with the semantics of
+/// `bisect_left_by`.
+pub(crate) fn locate_left_by<T, F>(segments: &[Vec<T>], mut f: F) -> (usize, usize)
+where
+    F: FnMut(&T) -> Ordering,
+{
+    // The first segment whose largest element is not less than the target.
+    let segment = bisect_left_slice_by(segments, .., |s| f(last(s)));
+    match segments.get(segment) {
+        Some(s) => (segment, bisect_left_slice_by(s, .., f)),
+        None => end(segments),
+    }
+}
+
+/// Return the `(segment, offset)` where a value should be inserted, with the semantics of
+/// `bisect_right_by`.
+pub(crate) fn locate_right_by<T, F>(segments: &[Vec<T>], mut f: F) -> (usize, usize)
+where
+    F: FnMut(&T) -> Ordering,
+{
+    // The first segment whose largest element is greater than the target.
+    let segment = bisect_right_slice_by(segments, .., |s| f(last(s)));
+    match segments.get(segment) {
+        Some(s) => (segment, bisect_right_slice_by(s, .., f)),
+        None => end(segments),
+    }
+}
+
+fn last<T>(segment: &[T]) -> &T {
+    segment.last().expect("segments must be non-empty")
+}
+
+fn end<T>(segments: &[Vec<T>]) -> (usize, usize) {
+    match segments.last() {
+        Some(s) => (segments.len() - 1, s.len()),
+        None => (0, 0),
+    }
+}
+
+fn position<T>(segments: &[Vec<T>], segment: usize, offset: usize) -> Position {
+    let before: usize = segments[..segment].iter().map(Vec::len).sum();
+    Position {
+        segment,
+        offset,
+        index: before + offset,
+    }
+}
+
+fn insert<T>(
+    segments: &mut Vec<Vec<T>>,
+    segment: usize,
+    offset: usize,
+    x: T,
+    max_len: usize,
+) -> Position {
+    assert!(max_len > 0, "segments must be allowed at least one element");
+    if segments.is_empty() {
+        segments.push(vec![x]);
+        return position(segments, 0, 0);
+    }
+    let pos = position(segments, segment, offset);
+    segments[segment].insert(offset, x);
+    if segments[segment].len() > max_len {
+        let half = segments[segment].len() / 2;
+        let tail = segments[segment].split_off(half);
+        segments.insert(segment + 1, tail);
+        if offset >= half {
+            return Position {
+                segment: segment + 1,
+                offset: offset - half,
+                ..pos
+            };
+        }
+    }
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn positions() {
+        let segments = vec![vec![1, 2, 2], vec![2, 5], vec![7, 9, 9]];
+        let at = |segment, offset, index| Position {
+            segment,
+            offset,
+            index,
+        };
+
+        assert_eq!(bisect_left(&segments, &2), at(0, 1, 1));
+        assert_eq!(bisect_right(&segments, &2), at(1, 1, 4));
+        assert_eq!(bisect_left(&segments, &6), at(2, 0, 5));
+        assert_eq!(bisect_right(&segments, &9), at(2, 3, 8));
+        assert_eq!(bisect_left(&segments, &0), at(0, 0, 0));
+        assert_eq!(bisect_left::<u32>(&[], &0), at(0, 0, 0));
+    }
+
+    #[test]
+    fn insort_splits() {
+        let mut segments = vec![];
+        for x in 0..10 {
+            insort_right(&mut segments, x, 4);
+        }
+        assert!(segments.iter().all(|s| !s.is_empty() && s.len() <= 4));
+        assert_eq!(segments.concat(), (0..10).collect::<Vec<_>>());
+
+        let pos = insort_left(&mut segments, 3, 4);
+        assert_eq!(segments[pos.segment][pos.offset], 3);
+        assert_eq!(pos.index, 3);
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_flat(
+            nums in prop::collection::vec(0..50_u32, 0..300),
+            probes in prop::collection::vec(0..51_u32, 1..20),
+            max_len in 1..16_usize
+        ) {
+            let mut segments = vec![];
+            let mut flat = vec![];
+            for (i, &x) in nums.iter().enumerate() {
+                let pos = if i % 2 == 0 {
+                    crate::insort_left(&mut flat, x);
+                    insort_left(&mut segments, x, max_len)
+                } else {
+                    crate::insort_right(&mut flat, x);
+                    insort_right(&mut segments, x, max_len)
+                };
+                prop_assert_eq!(segments[pos.segment][pos.offset], x);
+            }
+
+            prop_assert!(segments.iter().all(|s| !s.is_empty() && s.len() <= max_len));
+            prop_assert_eq!(segments.concat(), flat.clone());
+            for x in probes {
+                let left = bisect_left(&segments, &x);
+                let right = bisect_right(&segments, &x);
+                prop_assert_eq!(left.index, crate::bisect_left(&flat, &x));
+                prop_assert_eq!(right.index, crate::bisect_right(&flat, &x));
+            }
+        }
+    }
+}
@@ -0,0 +1,505 @@
+//! A sorted list with fast insertion and removal by value and by position.
+//!
+//! [`insort_left`](crate::insort_left) and friends shift every element after the insertion point,
+//! which dominates once a vector holds millions of elements. [`SortedList`] instead stores its
+//! elements as a list of sorted sublists of roughly `load` elements each, as done by Python's
+//! `sortedcontainers.SortedList`. Inserting or removing an element only shifts elements within
+//! one sublist, and a [Fenwick tree](https://en.wikipedia.org/wiki/Fenwick_tree) over the
+//! sublist lengths maps between positions and sublists in `O(log n)`.
+
+use crate::segmented::{locate_left_by, locate_right_by};
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::{Bound::*, Index, RangeBounds};
+
+/// Default number of elements per sublist.
+pub const DEFAULT_LOAD: usize = 1000;
+
+/// A list which keeps its elements in sorted order.
+///
+/// Positions and insertion points follow the free functions of this crate: for example
+/// [`bisect_left`](SortedList::bisect_left) returns the same index as
+/// [`bisect_left`](crate::bisect_left) on the equivalent sorted `Vec`.
+#[derive(Clone)]
+pub struct SortedList<T> {
+    lists: Vec<Vec<T>>,
+    index: Fenwick,
+    len: usize,
+    load: usize,
+}
+
+impl<T: Ord> SortedList<T> {
+    /// Create an empty list with the default load factor.
+    pub fn new() -> Self {
+        Self::with_load(DEFAULT_LOAD)
+    }
+
+    /// Create an empty list which keeps sublists between `load / 2` and `2 * load` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `load` is zero.
+    pub fn with_load(load: usize) -> Self {
+        assert!(load > 0, "load factor must be positive");
+        SortedList {
+            lists: Vec::new(),
+            index: Fenwick::default(),
+            len: 0,
+            load,
+        }
+    }
+
+    /// Insert `x` into the list, just *after* the *rightmost* occurence of `x` if any, as
+    /// [`insort_right`](crate::insort_right) does.
+    pub fn add(&mut self, x: T) {
+        if self.lists.is_empty() {
+            self.lists.push(vec![x]);
+            self.len = 1;
+            self.index = Fenwick::new(&self.lists);
+            return;
+        }
+        let (segment, offset) = locate_right_by(&self.lists, |p| x.cmp(p));
+        self.lists[segment].insert(offset, x);
+        self.len += 1;
+        if self.lists[segment].len() > 2 * self.load {
+            let tail = self.lists[segment].split_off(self.load);
+            self.lists.insert(segment + 1, tail);
+            self.index = Fenwick::new(&self.lists);
+        } else {
+            self.index.add(segment, 1);
+        }
+    }
+
+    /// Remove the *leftmost* occurence of `x` from the list, returning `true` if there was one.
+    pub fn remove(&mut self, x: &T) -> bool {
+        let (segment, offset) = locate_left_by(&self.lists, |p| p.cmp(x));
+        let found = self.lists.get(segment).and_then(|s| s.get(offset)) == Some(x);
+        if found {
+            self.remove_at(segment, offset);
+        }
+        found
+    }
+
+    /// Return `true` if the list contains `x`.
+    pub fn contains(&self, x: &T) -> bool {
+        self.get(self.bisect_left(x)) == Some(x)
+    }
+
+    /// Return the index where `x` should be inserted, just *before* the *leftmost* occurence of
+    /// `x` if any.
+    pub fn bisect_left(&self, x: &T) -> usize {
+        let (segment, offset) = locate_left_by(&self.lists, |p| p.cmp(x));
+        self.index.prefix(segment) + offset
+    }
+
+    /// Return the index where `x` should be inserted, just *after* the *rightmost* occurence of
+    /// `x` if any.
+    pub fn bisect_right(&self, x: &T) -> usize {
+        let (segment, offset) = locate_right_by(&self.lists, |p| x.cmp(p));
+        self.index.prefix(segment) + offset
+    }
+
+    /// Remove and return the element at `index`, or `None` if `index` is out of bounds.
+    pub fn pop(&mut self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None;
+        }
+        let (segment, offset) = self.index.find(index);
+        Some(self.remove_at(segment, offset))
+    }
+
+    /// Return an iterator over the elements `e` in `range`, in order.
+    ///
+    /// Included bounds are found with `bisect_left` for the start and `bisect_right` for the
+    /// end, and excluded bounds the other way around.
+    pub fn irange<R: RangeBounds<T>>(&self, range: R) -> Iter<'_, T> {
+        let lo = match range.start_bound() {
+            Unbounded => 0,
+            Included(x) => self.bisect_left(x),
+            Excluded(x) => self.bisect_right(x),
+        };
+        let hi = match range.end_bound() {
+            Unbounded => self.len,
+            Included(x) => self.bisect_right(x),
+            Excluded(x) => self.bisect_left(x),
+        };
+        self.iter_between(lo, hi.max(lo))
+    }
+}
+
+impl<T> SortedList<T> {
+    /// Return the number of elements in the list.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return `true` if the list has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return the load factor of the list.
+    pub fn load(&self) -> usize {
+        self.load
+    }
+
+    /// Return the element at `index`, or `None` if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        let (segment, offset) = self.index.find(index);
+        Some(&self.lists[segment][offset])
+    }
+
+    /// Return an iterator over the elements in order.
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.iter_between(0, self.len)
+    }
+
+    /// Return an iterator over the elements at the positions in `range`, in order.
+    ///
+    /// Like slicing in Python, bounds past the end of the list are clamped to its length.
+    pub fn islice<R: RangeBounds<usize>>(&self, range: R) -> Iter<'_, T> {
+        let hi = match range.end_bound() {
+            Unbounded => self.len,
+            Included(i) => i.saturating_add(1),
+            Excluded(i) => *i,
+        }
+        .min(self.len);
+        let lo = match range.start_bound() {
+            Unbounded => 0,
+            Included(i) => *i,
+            Excluded(i) => i.saturating_add(1),
+        }
+        .min(hi);
+        self.iter_between(lo, hi)
+    }
+
+    fn iter_between(&self, lo: usize, hi: usize) -> Iter<'_, T> {
+        let front = self.locate_position(lo);
+        let back = self.locate_position(hi);
+        Iter {
+            lists: &self.lists,
+            front,
+            back,
+            remaining: hi - lo,
+        }
+    }
+
+    /// Return the `(segment, offset)` of position `index`, which may be the end of the list.
+    fn locate_position(&self, index: usize) -> (usize, usize) {
+        if index < self.len {
+            self.index.find(index)
+        } else {
+            match self.lists.last() {
+                Some(s) => (self.lists.len() - 1, s.len()),
+                None => (0, 0),
+            }
+        }
+    }
+
+    fn remove_at(&mut self, segment: usize, offset: usize) -> T {
+        let x = self.lists[segment].remove(offset);
+        self.len -= 1;
+        let remaining = self.lists[segment].len();
+        if remaining == 0 {
+            self.lists.remove(segment);
+        } else if remaining < self.load / 2 && self.lists.len() > 1 {
+            // Merge with a neighbour, splitting again if the result is too long.
+            let left = segment.saturating_sub(1).min(self.lists.len() - 2);
+            let right = self.lists.remove(left + 1);
+            self.lists[left].extend(right);
+            if self.lists[left].len() > 2 * self.load {
+                let half = self.lists[left].len() / 2;
+                let tail = self.lists[left].split_off(half);
+                self.lists.insert(left + 1, tail);
+            }
+        } else {
+            self.index.sub(segment, 1);
+            return x;
+        }
+        self.index = Fenwick::new(&self.lists);
+        x
+    }
+}
+
+impl<T: Ord> Default for SortedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<usize> for SortedList<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("index out of bounds")
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SortedList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for SortedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = SortedList::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T: Ord> Extend<T> for SortedList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut values: Vec<T> = self.lists.drain(..).flatten().collect();
+        values.extend(iter);
+        // Stable, so equal elements already in the list stay before the new ones.
+        values.sort();
+        self.len = values.len();
+        let mut rest = values.into_iter();
+        while rest.len() > 0 {
+            self.lists.push(rest.by_ref().take(self.load).collect());
+        }
+        self.index = Fenwick::new(&self.lists);
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SortedList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+/// Iterator over a range of elements of a [`SortedList`].
+#[derive(Clone, Debug)]
+pub struct Iter<'a, T> {
+    lists: &'a [Vec<T>],
+    front: (usize, usize),
+    back: (usize, usize),
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let (segment, offset) = self.front;
+        self.remaining -= 1;
+        self.front = if offset + 1 < self.lists[segment].len() {
+            (segment, offset + 1)
+        } else {
+            (segment + 1, 0)
+        };
+        Some(&self.lists[segment][offset])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let (mut segment, mut offset) = self.back;
+        if offset == 0 {
+            segment -= 1;
+            offset = self.lists[segment].len();
+        }
+        self.remaining -= 1;
+        self.back = (segment, offset - 1);
+        Some(&self.lists[segment][offset - 1])
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+/// Fenwick tree over sublist lengths.
+#[derive(Clone, Debug, Default)]
+struct Fenwick {
+    // One-based: `tree[i]` is the sum of the `i & i.wrapping_neg()` lengths ending at `i - 1`.
+    tree: Vec<usize>,
+}
+
+impl Fenwick {
+    fn new<T>(lists: &[Vec<T>]) -> Self {
+        let mut tree = vec![0; lists.len() + 1];
+        for (i, list) in lists.iter().enumerate() {
+            let i = i + 1;
+            tree[i] += list.len();
+            let parent = i + (i & i.wrapping_neg());
+            if parent < tree.len() {
+                tree[parent] += tree[i];
+            }
+        }
+        Fenwick { tree }
+    }
+
+    fn add(&mut self, segment: usize, n: usize) {
+        let mut i = segment + 1;
+        while i < self.tree.len() {
+            self.tree[i] += n;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    fn sub(&mut self, segment: usize, n: usize) {
+        let mut i = segment + 1;
+        while i < self.tree.len() {
+            self.tree[i] -= n;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// Return the total length of the first `segment` sublists.
+    fn prefix(&self, segment: usize) -> usize {
+        let mut i = segment;
+        let mut sum = 0;
+        while i > 0 {
+            sum += self.tree[i];
+            i -= i & i.wrapping_neg();
+        }
+        sum
+    }
+
+    /// Return the `(segment, offset)` of position `index`, which must be in bounds.
+    fn find(&self, index: usize) -> (usize, usize) {
+        let n = self.tree.len() - 1;
+        let mut segment = 0;
+        let mut rest = index;
+        let mut step = n.next_power_of_two();
+        while step > 0 {
+            let next = segment + step;
+            if next <= n && self.tree[next] <= rest {
+                segment = next;
+                rest -= self.tree[next];
+            }
+            step /= 2;
+        }
+        (segment, rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn operations() {
+        let mut list = SortedList::with_load(2);
+        for x in [5, 1, 4, 1, 5, 9, 2, 6, 5, 3].iter() {
+            list.add(*x);
+        }
+        assert_eq!(format!("{:?}", list), "[1, 1, 2, 3, 4, 5, 5, 5, 6, 9]");
+        assert_eq!(list.len(), 10);
+        assert_eq!(list[3], 3);
+        assert_eq!(list.get(10), None);
+
+        assert_eq!(list.bisect_left(&5), 5);
+        assert_eq!(list.bisect_right(&5), 8);
+        assert!(list.contains(&6));
+        assert!(!list.contains(&7));
+
+        assert!(list.remove(&5));
+        assert!(!list.remove(&7));
+        assert_eq!(list.pop(0), Some(1));
+        assert_eq!(list.pop(8), None);
+        assert_eq!(
+            list.iter().copied().collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5, 5, 6, 9]
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        let list: SortedList<u32> = (0..100).map(|x| x / 2).collect();
+        assert_eq!(list.irange(10..12).count(), 4);
+        assert_eq!(list.irange(10..=12).count(), 6);
+        assert_eq!(list.irange(..).count(), 100);
+        let (lo, hi) = (12, 10);
+        assert_eq!(list.irange(lo..hi).count(), 0);
+        assert_eq!(list.islice(98..).copied().collect::<Vec<_>>(), [49, 49]);
+        assert_eq!(list.islice(95..200).len(), 5);
+        assert_eq!(
+            list.islice(3..=4).rev().copied().collect::<Vec<_>>(),
+            [2, 1]
+        );
+    }
+
+    #[derive(Clone, Debug)]
+    enum Op {
+        Add(u32),
+        Remove(u32),
+        Pop(usize),
+    }
+
+    fn arb_op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            3 => (0..50_u32).prop_map(Op::Add),
+            1 => (0..50_u32).prop_map(Op::Remove),
+            1 => (0..200_usize).prop_map(Op::Pop),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_vec(
+            ops in prop::collection::vec(arb_op(), 0..400),
+            load in 1..8_usize,
+            lo in 0..60_usize,
+            hi in 0..60_usize
+        ) {
+            let mut list = SortedList::with_load(load);
+            let mut model = Vec::new();
+            for op in ops {
+                match op {
+                    Op::Add(x) => {
+                        list.add(x);
+                        crate::insort_right(&mut model, x);
+                    }
+                    Op::Remove(x) => {
+                        let i = crate::bisect_left(&model, &x);
+                        let found = model.get(i) == Some(&x);
+                        if found {
+                            model.remove(i);
+                        }
+                        prop_assert_eq!(list.remove(&x), found);
+                    }
+                    Op::Pop(i) => {
+                        let expected = if i < model.len() { Some(model.remove(i)) } else { None };
+                        prop_assert_eq!(list.pop(i), expected);
+                    }
+                }
+                prop_assert_eq!(list.len(), model.len());
+            }
+
+            prop_assert!(list.iter().eq(model.iter()));
+            prop_assert!(list.iter().rev().eq(model.iter().rev()));
+            for (i, x) in model.iter().enumerate() {
+                prop_assert_eq!(&list[i], x);
+            }
+            for x in 0..51 {
+                prop_assert_eq!(list.bisect_left(&x), crate::bisect_left(&model, &x));
+                prop_assert_eq!(list.bisect_right(&x), crate::bisect_right(&model, &x));
+            }
+
+            let (lo32, hi32) = (lo as u32, hi as u32);
+            let expected = model.iter().filter(|&&x| lo32 <= x && x < hi32);
+            prop_assert!(list.irange(lo32..hi32).eq(expected));
+            let expected = model.iter().skip(lo).take(hi.saturating_sub(lo));
+            prop_assert!(list.islice(lo..hi).eq(expected));
+        }
+    }
+}
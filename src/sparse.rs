@@ -0,0 +1,401 @@
+//! Sparse index over large sorted files.
+//!
+//! A [`SparseIndex`] keeps every `n`th line or record of a sorted file, or the first line of
+//! every block of bytes, along with its offset, in the manner of the index blocks of an
+//! SSTable. A lookup bisects the sampled keys in memory to find the one block of the file which
+//! can hold the answer, then bisects only that block on disk. The index is small enough to keep
+//! in memory and can be persisted next to the file it describes.
+
+use crate::record::RecordFile;
+use crate::{bisect_left_by, bisect_right_by, file};
+use std::cmp::Ordering;
+use std::io::{self, BufRead, BufReader, Read, Seek, Write};
+use std::ops::Range;
+
+const MAGIC: &[u8; 4] = b"BSIX";
+const VERSION: u8 = 1;
+
+/// How the indexed file is divided into elements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// Lines ending in `\n`, as searched by the [`mod@file`] module.
+    Lines,
+    /// Records of the given size, as searched by [`RecordFile`].
+    Records(usize),
+}
+
+/// Sampled elements of a sorted file, with their byte offsets.
+///
+/// Lookups take comparators over whole lines, stripped of their line ending, or whole records,
+/// and return byte offsets into the file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SparseIndex {
+    layout: Layout,
+    entries: Vec<(Vec<u8>, u64)>,
+    end: u64,
+}
+
+impl SparseIndex {
+    /// Build an index of every `every`th line of `reader`, starting with the first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every` is zero.
+    pub fn from_lines<R: Read>(reader: R, every: usize) -> io::Result<Self> {
+        assert!(every > 0, "sampling interval must be positive");
+        let mut i = 0;
+        Self::sample_lines(reader, |_| {
+            i += 1;
+            (i - 1) % every == 0
+        })
+    }
+
+    /// Build an index of the first line starting in each `block_size` bytes of `reader`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is zero.
+    pub fn from_line_blocks<R: Read>(reader: R, block_size: u64) -> io::Result<Self> {
+        assert!(block_size > 0, "block size must be positive");
+        let mut next_block = 0;
+        Self::sample_lines(reader, |offset| {
+            let sample = offset >= next_block;
+            if sample {
+                next_block = (offset / block_size + 1) * block_size;
+            }
+            sample
+        })
+    }
+
+    /// Build an index of every `every`th record of `record_size` bytes of `reader`, starting
+    /// with the first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `InvalidData` if the length of `reader` is not a multiple of
+    /// `record_size`, and any error from reading `reader`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `record_size` or `every` is zero.
+    pub fn from_records<R: Read>(
+        mut reader: R,
+        record_size: usize,
+        every: usize,
+    ) -> io::Result<Self> {
+        assert!(record_size > 0, "record size must be positive");
+        assert!(every > 0, "sampling interval must be positive");
+        let mut entries = vec![];
+        let mut record = vec![0; record_size];
+        let mut end = 0;
+        for i in 0.. {
+            let n = read_full(&mut reader, &mut record)?;
+            if n == 0 {
+                break;
+            } else if n < record_size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "length is not a multiple of the record size",
+                ));
+            }
+            if i % every == 0 {
+                entries.push((record.clone(), end));
+            }
+            end += record_size as u64;
+        }
+        Ok(SparseIndex {
+            layout: Layout::Records(record_size),
+            entries,
+            end,
+        })
+    }
+
+    fn sample_lines<R, S>(reader: R, mut sample: S) -> io::Result<Self>
+    where
+        R: Read,
+        S: FnMut(u64) -> bool,
+    {
+        let mut reader = BufReader::new(reader);
+        let mut entries = vec![];
+        let mut line = vec![];
+        let mut end = 0;
+        loop {
+            line.clear();
+            let n = reader.read_until(b'\n', &mut line)?;
+            if n == 0 {
+                break;
+            }
+            if sample(end) {
+                let stripped = line.strip_suffix(b"\n").unwrap_or(&line);
+                let stripped = stripped.strip_suffix(b"\r").unwrap_or(stripped);
+                entries.push((stripped.to_vec(), end));
+            }
+            end += n as u64;
+        }
+        Ok(SparseIndex {
+            layout: Layout::Lines,
+            entries,
+            end,
+        })
+    }
+
+    /// Return how the indexed file is divided into elements.
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Return the number of sampled elements.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Return `true` if no elements were sampled.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Return the length in bytes of the indexed file.
+    pub fn file_len(&self) -> u64 {
+        self.end
+    }
+
+    /// Return the bytes of the file holding the position
+    /// [`bisect_left_by`](SparseIndex::bisect_left_by) would find.
+    pub fn block_left_by<F>(&self, mut f: F) -> Range<u64>
+    where
+        F: FnMut(&[u8]) -> Ordering,
+    {
+        self.block(bisect_left_by(&self.entries, |e| f(&e.0)))
+    }
+
+    /// Return the bytes of the file holding the position
+    /// [`bisect_right_by`](SparseIndex::bisect_right_by) would find.
+    pub fn block_right_by<F>(&self, mut f: F) -> Range<u64>
+    where
+        F: FnMut(&[u8]) -> Ordering,
+    {
+        self.block(bisect_right_by(&self.entries, |e| f(&e.0)))
+    }
+
+    /// Return the offset of the first element of `reader` for which `f` does not return `Less`,
+    /// reading a single block of the file.
+    ///
+    /// The comparator function follows the same contract as
+    /// [`bisect_left_by`].
+    pub fn bisect_left_by<R, F>(&self, reader: &mut R, mut f: F) -> io::Result<u64>
+    where
+        R: Read + Seek,
+        F: FnMut(&[u8]) -> Ordering,
+    {
+        let block = self.block_left_by(&mut f);
+        match self.layout {
+            Layout::Lines => file::bisect_left_slice_by(reader, block, f),
+            Layout::Records(size) => {
+                let (mut records, within) = self.records(reader, size, block)?;
+                let i = records.bisect_left_slice_by(within, f)?;
+                Ok(i * size as u64)
+            }
+        }
+    }
+
+    /// Return the offset of the first element of `reader` for which `f` returns `Less`, reading
+    /// a single block of the file.
+    ///
+    /// The comparator function follows the same contract as
+    /// [`bisect_right_by`].
+    pub fn bisect_right_by<R, F>(&self, reader: &mut R, mut f: F) -> io::Result<u64>
+    where
+        R: Read + Seek,
+        F: FnMut(&[u8]) -> Ordering,
+    {
+        let block = self.block_right_by(&mut f);
+        match self.layout {
+            Layout::Lines => file::bisect_right_slice_by(reader, block, f),
+            Layout::Records(size) => {
+                let (mut records, within) = self.records(reader, size, block)?;
+                let i = records.bisect_right_slice_by(within, f)?;
+                Ok(i * size as u64)
+            }
+        }
+    }
+
+    /// Serialize the index to `w`.
+    pub fn write_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        let record_size = match self.layout {
+            Layout::Lines => 0,
+            Layout::Records(size) => size as u64,
+        };
+        for n in &[record_size, self.end, self.entries.len() as u64] {
+            w.write_all(&n.to_le_bytes())?;
+        }
+        for (key, offset) in &self.entries {
+            w.write_all(&offset.to_le_bytes())?;
+            w.write_all(&(key.len() as u64).to_le_bytes())?;
+            w.write_all(key)?;
+        }
+        Ok(())
+    }
+
+    /// Deserialize an index previously written by [`write_to`](SparseIndex::write_to).
+    pub fn read_from<R: Read>(mut r: R) -> io::Result<Self> {
+        let mut header = [0; 5];
+        r.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(invalid("not a sparse index"));
+        }
+        let layout = match read_u64(&mut r)? {
+            0 => Layout::Lines,
+            size => Layout::Records(size as usize),
+        };
+        let end = read_u64(&mut r)?;
+        let count = read_u64(&mut r)?;
+        let mut entries = vec![];
+        let mut last = 0;
+        for _ in 0..count {
+            let offset = read_u64(&mut r)?;
+            let len = read_u64(&mut r)?;
+            if offset < last || offset > end {
+                return Err(invalid("offsets out of order"));
+            }
+            let mut key = vec![];
+            (&mut r).take(len).read_to_end(&mut key)?;
+            if key.len() as u64 != len {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            entries.push((key, offset));
+            last = offset;
+        }
+        Ok(SparseIndex {
+            layout,
+            entries,
+            end,
+        })
+    }
+
+    /// Return the bytes between the sample before `i` and sample `i`.
+    fn block(&self, i: usize) -> Range<u64> {
+        let start = i.checked_sub(1).map_or(0, |i| self.entries[i].1);
+        let end = self.entries.get(i).map_or(self.end, |e| e.1);
+        start..end
+    }
+
+    fn records<'r, R: Read + Seek>(
+        &self,
+        reader: &'r mut R,
+        size: usize,
+        block: Range<u64>,
+    ) -> io::Result<(RecordFile<&'r mut R, ()>, Range<u64>)> {
+        // Searched with comparators over whole records, so no key is needed.
+        let records = RecordFile::new(reader, size, ())?;
+        let size = size as u64;
+        Ok((records, block.start / size..block.end / size))
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Read until `buf` is full or the end of `reader`, returning the number of bytes read.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match reader.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(m) => n += m,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn lines() {
+        let text: String = (0..1000).map(|i| format!("key{:04}\n", i)).collect();
+        let index = SparseIndex::from_lines(text.as_bytes(), 100).unwrap();
+        assert_eq!(index.len(), 10);
+        assert_eq!(index.block_left_by(|l| l.cmp(&b"key0250"[..])), 1600..2400);
+
+        let mut r = Cursor::new(text.as_bytes());
+        let offset = index
+            .bisect_left_by(&mut r, |l| l.cmp(&b"key0250"[..]))
+            .unwrap();
+        assert_eq!(offset, 250 * 8);
+
+        let offset = index
+            .bisect_right_by(&mut r, |l| b"key0999"[..].cmp(l))
+            .unwrap();
+        assert_eq!(offset, 8000);
+    }
+
+    #[test]
+    fn records() {
+        let data: Vec<u8> = (0..100_u16).flat_map(|i| (i / 2).to_be_bytes()).collect();
+        let index = SparseIndex::from_records(&data[..], 2, 8).unwrap();
+        assert_eq!(index.layout(), Layout::Records(2));
+        assert_eq!(index.len(), 13);
+
+        let mut r = Cursor::new(&data);
+        let key = 17_u16.to_be_bytes();
+        assert_eq!(index.bisect_left_by(&mut r, |e| e.cmp(&key)).unwrap(), 68);
+        assert_eq!(
+            index.bisect_right_by(&mut r, |e| key[..].cmp(e)).unwrap(),
+            72
+        );
+
+        assert!(SparseIndex::from_records(&data[1..], 2, 8).is_err());
+    }
+
+    #[test]
+    fn persistence() {
+        let text = b"a\nb\r\nc\nd";
+        let index = SparseIndex::from_line_blocks(&text[..], 3).unwrap();
+        assert_eq!(index.len(), 3);
+
+        let mut buf = vec![];
+        index.write_to(&mut buf).unwrap();
+        assert_eq!(SparseIndex::read_from(&buf[..]).unwrap(), index);
+        assert!(SparseIndex::read_from(&buf[..buf.len() - 1]).is_err());
+        assert!(SparseIndex::read_from(&b"BSLI\x01"[..]).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_file_bisect(
+            mut lines in prop::collection::vec("[a-c]{0,4}", 0..200),
+            key in "[a-c]{0,4}",
+            every in 1..20_usize,
+            block_size in 1..64_u64
+        ) {
+            lines.sort();
+            let text: String = lines.iter().map(|l| format!("{}\n", l)).collect();
+            let key = key.as_bytes();
+            let mut r = Cursor::new(text.as_bytes());
+            let left = file::bisect_left(&mut r, key).unwrap();
+            let right = file::bisect_right(&mut r, key).unwrap();
+
+            for index in &[
+                SparseIndex::from_lines(text.as_bytes(), every).unwrap(),
+                SparseIndex::from_line_blocks(text.as_bytes(), block_size).unwrap(),
+            ] {
+                prop_assert_eq!(index.bisect_left_by(&mut r, |l| l.cmp(key)).unwrap(), left);
+                prop_assert_eq!(index.bisect_right_by(&mut r, |l| key.cmp(l)).unwrap(), right);
+            }
+        }
+    }
+}
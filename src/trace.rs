@@ -0,0 +1,254 @@
+//! Instrumented bisection recording the probes a search makes.
+//!
+//! The slice functions at the root of the crate, such as
+//! [`bisect_left_slice_by`](crate::bisect_left_slice_by), are implemented in terms of the
+//! `_observed` variants in this module, which report each comparison to an [`Observer`]. They
+//! use the no-op observer `()`, which compiles away entirely. The other modules have their own
+//! search loops and are not instrumented.
+
+use crate::bounds_to_indices;
+use std::cmp::Ordering;
+use std::ops::RangeBounds;
+
+/// Receives events from an instrumented search.
+///
+/// All methods default to doing nothing.
+pub trait Observer {
+    /// Called once before searching `lo..hi`.
+    fn start(&mut self, _lo: usize, _hi: usize) {}
+
+    /// Called after comparing the element at `index`, with the result of the comparison.
+    fn probe(&mut self, _index: usize, _outcome: Ordering) {}
+
+    /// Called once when the search has narrowed the range down to `lo..hi`.
+    fn finish(&mut self, _lo: usize, _hi: usize) {}
+}
+
+impl Observer for () {}
+
+impl<O: Observer + ?Sized> Observer for &mut O {
+    fn start(&mut self, lo: usize, hi: usize) {
+        (**self).start(lo, hi)
+    }
+
+    fn probe(&mut self, index: usize, outcome: Ordering) {
+        (**self).probe(index, outcome)
+    }
+
+    fn finish(&mut self, lo: usize, hi: usize) {
+        (**self).finish(lo, hi)
+    }
+}
+
+/// A single comparison made during a search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Probe {
+    /// Index of the element compared.
+    pub index: usize,
+    /// Result of the comparison, as returned by the comparator function.
+    pub outcome: Ordering,
+}
+
+/// An [`Observer`] recording every event of a search.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trace {
+    /// Range the search started with.
+    pub start: (usize, usize),
+    /// Probes in the order they were made.
+    pub probes: Vec<Probe>,
+    /// Range the search finished with.
+    pub finish: (usize, usize),
+}
+
+impl Trace {
+    /// Create an empty trace.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the number of comparisons made.
+    pub fn comparisons(&self) -> usize {
+        self.probes.len()
+    }
+
+    /// Return the probed indices in the order they were probed.
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.probes.iter().map(|p| p.index)
+    }
+
+    /// Return `true` if the recorded outcomes are consistent with a sorted slice.
+    ///
+    /// Ordered by index, the outcomes of a consistent comparator never decrease (as for
+    /// `bisect_left_by`) or never increase (as for `bisect_right_by`). A `false` result means the
+    /// comparator does not implement an order consistent with the slice and the result of the
+    /// search is meaningless.
+    pub fn is_monotonic(&self) -> bool {
+        let mut probes = self.probes.clone();
+        probes.sort_by_key(|p| p.index);
+        let pairs = || probes.windows(2).map(|w| (w[0], w[1]));
+        let consistent = |(a, b): (Probe, Probe)| a.index != b.index || a.outcome == b.outcome;
+        pairs().all(consistent)
+            && (pairs().all(|(a, b)| a.outcome <= b.outcome)
+                || pairs().all(|(a, b)| a.outcome >= b.outcome))
+    }
+}
+
+impl Observer for Trace {
+    fn start(&mut self, lo: usize, hi: usize) {
+        self.start = (lo, hi);
+    }
+
+    fn probe(&mut self, index: usize, outcome: Ordering) {
+        self.probes.push(Probe { index, outcome });
+    }
+
+    fn finish(&mut self, lo: usize, hi: usize) {
+        self.finish = (lo, hi);
+    }
+}
+
+/// Return the index where `x` should be inserted in `a`, assuming `a` is sorted, along with a
+/// trace of the search.
+///
+/// See [`bisect_left`](crate::bisect_left).
+pub fn bisect_left_traced<T: Ord>(a: &[T], x: &T) -> (usize, Trace) {
+    let mut trace = Trace::new();
+    let i = bisect_left_slice_by_observed(a, .., |p| p.cmp(x), &mut trace);
+    (i, trace)
+}
+
+/// Return the index where `x` should be inserted in `a`, assuming `a` is sorted, along with a
+/// trace of the search.
+///
+/// See [`bisect_right`](crate::bisect_right).
+pub fn bisect_right_traced<T: Ord>(a: &[T], x: &T) -> (usize, Trace) {
+    let mut trace = Trace::new();
+    let i = bisect_right_slice_by_observed(a, .., |p| x.cmp(p), &mut trace);
+    (i, trace)
+}
+
+/// [`bisect_right_slice_by`](crate::bisect_right_slice_by), reporting each comparison to
+/// `observer`.
+///
+/// # Panics
+///
+/// Panics if `within` is out of bounds of `a`.
+pub fn bisect_right_slice_by_observed<T, I, F, O>(
+    a: &[T],
+    within: I,
+    mut f: F,
+    mut observer: O,
+) -> usize
+where
+    I: RangeBounds<usize>,
+    F: FnMut(&T) -> Ordering,
+    O: Observer,
+{
+    let (mut lo, mut hi) = bounds_to_indices(a, within);
+    observer.start(lo, hi);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let cmp = f(&a[mid]);
+        observer.probe(mid, cmp);
+        if cmp == Ordering::Less {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    observer.finish(lo, hi);
+    lo
+}
+
+/// [`bisect_left_slice_by`](crate::bisect_left_slice_by), reporting each comparison to
+/// `observer`.
+///
+/// # Panics
+///
+/// Panics if `within` is out of bounds of `a`.
+pub fn bisect_left_slice_by_observed<T, I, F, O>(
+    a: &[T],
+    within: I,
+    mut f: F,
+    mut observer: O,
+) -> usize
+where
+    I: RangeBounds<usize>,
+    F: FnMut(&T) -> Ordering,
+    O: Observer,
+{
+    let (mut lo, mut hi) = bounds_to_indices(a, within);
+    observer.start(lo, hi);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let cmp = f(&a[mid]);
+        observer.probe(mid, cmp);
+        if cmp == Ordering::Less {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    observer.finish(lo, hi);
+    lo
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bisect_left, bisect_right};
+    use proptest::prelude::*;
+
+    #[test]
+    fn records_probes() {
+        let a = [1, 2, 3, 4, 5, 6, 7];
+        let (i, trace) = bisect_left_traced(&a, &6);
+
+        assert_eq!(i, 5);
+        assert_eq!(trace.start, (0, 7));
+        assert_eq!(trace.finish, (5, 5));
+        assert_eq!(trace.indices().collect::<Vec<_>>(), vec![3, 5, 4]);
+        assert_eq!(
+            trace.probes.iter().map(|p| p.outcome).collect::<Vec<_>>(),
+            vec![Ordering::Less, Ordering::Equal, Ordering::Less]
+        );
+        assert!(trace.is_monotonic());
+    }
+
+    #[test]
+    fn detects_inconsistent_comparator() {
+        let a = [1, 2, 3, 4, 5, 6, 7];
+        let mut trace = Trace::new();
+        // Claims the middle element is the target but its left neighbour is past the target.
+        bisect_left_slice_by_observed(
+            &a,
+            ..,
+            |&p| match p {
+                4 => Ordering::Equal,
+                2 => Ordering::Greater,
+                _ => Ordering::Less,
+            },
+            &mut trace,
+        );
+        assert!(!trace.is_monotonic());
+    }
+
+    proptest! {
+        #[test]
+        fn test_traced_matches_bisect(
+            mut nums in prop::collection::vec(any::<u32>(), 0..500),
+            num in any::<u32>()
+        ) {
+            nums.sort();
+
+            let (left, trace) = bisect_left_traced(&nums, &num);
+            prop_assert_eq!(left, bisect_left(&nums, &num));
+            prop_assert!(trace.comparisons() <= 64 - nums.len().leading_zeros() as usize);
+            prop_assert!(trace.is_monotonic());
+
+            let (right, trace) = bisect_right_traced(&nums, &num);
+            prop_assert_eq!(right, bisect_right(&nums, &num));
+            prop_assert!(trace.is_monotonic());
+        }
+    }
+}
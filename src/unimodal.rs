@@ -0,0 +1,240 @@
+//! Search for the extremum of unimodal sequences and functions.
+//!
+//! Where bisection finds the boundary of a monotone predicate, ternary and golden-section search
+//! find the peak (or trough) of a sequence which strictly increases and then strictly decreases,
+//! such as throughput as a function of batch size. Each step compares two interior points and
+//! discards the part of the range which cannot contain the extremum.
+
+use std::cmp::{Ordering, Reverse};
+use std::ops::Range;
+
+/// Location and value of an extremum, along with the number of evaluations used to find it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Extremum<X, V> {
+    /// Point at which the extremum was found.
+    pub argument: X,
+    /// Value of the function at `argument`.
+    pub value: V,
+    /// Number of times the function was evaluated.
+    pub evaluations: usize,
+}
+
+/// Return the index in `within` where `f` is greatest, assuming `f` is strictly increasing and
+/// then strictly decreasing over `within`, or `None` if `within` is empty.
+///
+/// The result is unspecified if `f` has plateaus away from its maximum.
+pub fn ternary_search_max<T, F>(within: Range<usize>, f: F) -> Option<Extremum<usize, T>>
+where
+    T: Ord,
+    F: FnMut(usize) -> T,
+{
+    ternary_search_by(within, f, T::cmp)
+}
+
+/// Return the index in `within` where `f` is least, assuming `f` is strictly decreasing and
+/// then strictly increasing over `within`, or `None` if `within` is empty.
+///
+/// The result is unspecified if `f` has plateaus away from its minimum.
+pub fn ternary_search_min<T, F>(within: Range<usize>, f: F) -> Option<Extremum<usize, T>>
+where
+    T: Ord,
+    F: FnMut(usize) -> T,
+{
+    ternary_search_by(within, f, |a, b| Reverse(a).cmp(&Reverse(b)))
+}
+
+/// Return the index of the greatest element of `a`, assuming `a` is strictly increasing and then
+/// strictly decreasing, or `None` if `a` is empty.
+pub fn argmax_unimodal<T: Ord>(a: &[T]) -> Option<usize> {
+    ternary_search_max(0..a.len(), |i| &a[i]).map(|e| e.argument)
+}
+
+/// Return the index of the least element of `a`, assuming `a` is strictly decreasing and then
+/// strictly increasing, or `None` if `a` is empty.
+pub fn argmin_unimodal<T: Ord>(a: &[T]) -> Option<usize> {
+    ternary_search_min(0..a.len(), |i| &a[i]).map(|e| e.argument)
+}
+
+/// Ternary search for the index where `f` is greatest according to `cmp`.
+fn ternary_search_by<T, F, C>(
+    within: Range<usize>,
+    mut f: F,
+    mut cmp: C,
+) -> Option<Extremum<usize, T>>
+where
+    F: FnMut(usize) -> T,
+    C: FnMut(&T, &T) -> Ordering,
+{
+    let (mut lo, mut hi) = (within.start, within.end.max(within.start));
+    let mut evaluations = 0;
+    let mut eval = |i| {
+        evaluations += 1;
+        f(i)
+    };
+
+    while hi - lo > 3 {
+        let third = (hi - lo) / 3;
+        let (m1, m2) = (lo + third, hi - 1 - third);
+        if cmp(&eval(m1), &eval(m2)) == Ordering::Less {
+            lo = m1 + 1;
+        } else {
+            hi = m2;
+        }
+    }
+
+    let mut best: Option<(usize, T)> = None;
+    for i in lo..hi {
+        let value = eval(i);
+        if best
+            .as_ref()
+            .is_none_or(|b| cmp(&value, &b.1) == Ordering::Greater)
+        {
+            best = Some((i, value));
+        }
+    }
+    best.map(|(argument, value)| Extremum {
+        argument,
+        value,
+        evaluations,
+    })
+}
+
+/// Return the point in `lo..=hi` where `f` is greatest, to within `tolerance`, assuming `f` is
+/// strictly increasing and then strictly decreasing over the interval.
+///
+/// # Panics
+///
+/// Panics if `tolerance` is not positive.
+pub fn golden_section_max<F>(lo: f64, hi: f64, tolerance: f64, f: F) -> Extremum<f64, f64>
+where
+    F: FnMut(f64) -> f64,
+{
+    golden_section_by(lo, hi, tolerance, f, |a, b| a < b)
+}
+
+/// Return the point in `lo..=hi` where `f` is least, to within `tolerance`, assuming `f` is
+/// strictly decreasing and then strictly increasing over the interval.
+///
+/// # Panics
+///
+/// Panics if `tolerance` is not positive.
+pub fn golden_section_min<F>(lo: f64, hi: f64, tolerance: f64, f: F) -> Extremum<f64, f64>
+where
+    F: FnMut(f64) -> f64,
+{
+    golden_section_by(lo, hi, tolerance, f, |a, b| a > b)
+}
+
+/// Golden-section search for the point where `f` is greatest according to `less`.
+///
+/// Interior points divide the interval in the golden ratio so that one of them can be reused in
+/// the next step, needing a single new evaluation per step.
+fn golden_section_by<F, L>(
+    lo: f64,
+    hi: f64,
+    tolerance: f64,
+    mut f: F,
+    less: L,
+) -> Extremum<f64, f64>
+where
+    F: FnMut(f64) -> f64,
+    L: Fn(f64, f64) -> bool,
+{
+    assert!(tolerance > 0.0, "tolerance must be positive");
+    let inv_phi = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut a, mut b) = if lo <= hi { (lo, hi) } else { (hi, lo) };
+
+    let mut x1 = b - inv_phi * (b - a);
+    let mut x2 = a + inv_phi * (b - a);
+    let (mut f1, mut f2) = (f(x1), f(x2));
+    let mut evaluations = 2;
+
+    while b - a > tolerance {
+        if less(f1, f2) {
+            a = x1;
+            x1 = x2;
+            f1 = f2;
+            x2 = a + inv_phi * (b - a);
+            f2 = f(x2);
+        } else {
+            b = x2;
+            x2 = x1;
+            f2 = f1;
+            x1 = b - inv_phi * (b - a);
+            f1 = f(x1);
+        }
+        evaluations += 1;
+    }
+
+    let argument = (a + b) / 2.0;
+    Extremum {
+        argument,
+        value: f(argument),
+        evaluations: evaluations + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn empty() {
+        assert_eq!(ternary_search_max(3..3, |i| i), None);
+        let (start, end) = (5, 3);
+        assert_eq!(ternary_search_max(start..end, |i| i), None);
+        assert_eq!(ternary_search_min(start..end, |i| i), None);
+        assert_eq!(argmax_unimodal::<u32>(&[]), None);
+    }
+
+    #[test]
+    fn slices() {
+        assert_eq!(argmax_unimodal(&[1, 3, 7, 9, 4, 2]), Some(3));
+        assert_eq!(argmax_unimodal(&[1, 2, 3]), Some(2));
+        assert_eq!(argmax_unimodal(&[3, 2, 1]), Some(0));
+        assert_eq!(argmin_unimodal(&[9, 4, 1, 2, 8]), Some(2));
+    }
+
+    #[test]
+    fn integer_domain() {
+        let peak = 1_234_567;
+        let result = ternary_search_max(0..10_000_000, |i: usize| {
+            Reverse((i as i64 - peak as i64).abs())
+        })
+        .unwrap();
+        assert_eq!(result.argument, peak);
+        assert_eq!(result.value, Reverse(0));
+        assert!(result.evaluations < 100);
+    }
+
+    #[test]
+    fn golden_section() {
+        let result = golden_section_max(0.0, 10.0, 1e-9, |x| -(x - 2.5) * (x - 2.5) + 1.0);
+        assert!((result.argument - 2.5).abs() < 1e-6);
+        assert!((result.value - 1.0).abs() < 1e-9);
+        assert!(result.evaluations < 60);
+
+        let result = golden_section_min(-5.0, 5.0, 1e-9, |x| x.cosh());
+        assert!(result.argument.abs() < 1e-6);
+    }
+
+    proptest! {
+        #[test]
+        fn test_finds_peak(
+            up in prop::collection::btree_set(any::<i32>(), 1..200),
+            down in prop::collection::btree_set(any::<i32>(), 0..200),
+        ) {
+            // Strictly increasing then strictly decreasing, with a unique maximum.
+            let peak = *up.iter().next_back().unwrap();
+            let mut a: Vec<i32> = up.into_iter().collect();
+            a.extend(down.iter().rev().filter(|&&x| x < peak));
+
+            let expected = a.iter().position(|&x| x == peak);
+            prop_assert_eq!(argmax_unimodal(&a), expected);
+
+            let negated: Vec<i64> = a.iter().map(|&x| -(x as i64)).collect();
+            prop_assert_eq!(argmin_unimodal(&negated), expected);
+        }
+    }
+}